@@ -300,7 +300,7 @@ impl RiftDevnetBuilder {
         let circuit_verification_key_hash = rift_sdk::get_rift_program_hash();
         let (ethereum_devnet, deployment_block_number) = crate::evm_devnet::EthDevnet::setup(
             circuit_verification_key_hash,
-            contract_data_engine.get_mmr_root().await.unwrap(),
+            contract_data_engine.get_mmr_root().await.unwrap().into(),
             tip_block_leaf,
             fork_config,
             interactive,
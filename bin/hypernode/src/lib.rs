@@ -1,5 +1,8 @@
+pub mod maintenance;
+pub mod proving_budget;
 pub mod release_watchtower;
 pub mod swap_watchtower;
+pub mod tip_oracle;
 pub mod txn_broadcast;
 
 use alloy::primitives::Address;
@@ -10,17 +13,26 @@ use bitcoincore_rpc_async::{Auth, RpcApi};
 use checkpoint_downloader::decompress_checkpoint_file;
 use clap::Parser;
 use eyre::Result;
+use maintenance::MaintenanceWindow;
 use release_watchtower::ReleaseWatchtower;
 use rift_sdk::proof_generator::{ProofGeneratorType, RiftProofGenerator};
-use rift_sdk::{create_websocket_provider, create_websocket_wallet_provider, DatabaseLocation};
+use rift_sdk::{
+    create_websocket_provider, create_websocket_wallet_provider_with_reconnect_policy,
+    DatabaseLocation, WebsocketWalletProvider, WsReconnectPolicy,
+};
 use serde_json;
+use std::collections::HashSet;
 use std::fs::File;
+use std::future::Future;
 use std::io::{BufReader, Read};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use swap_watchtower::SwapWatchtower;
+use swap_watchtower::{SwapWatchtower, SwapWatchtowerConfig};
+use tip_oracle::{EsploraTipOracle, TipOracleConfig};
 use tokio::runtime::Runtime;
+use tokio::sync::watch;
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
@@ -48,6 +60,11 @@ pub struct HypernodeArgs {
     #[arg(long, env)]
     pub evm_ws_rpc: String,
 
+    /// Additional Ethereum RPC websocket URLs, comma delimited, that transactions are sent
+    /// through in order when `evm_ws_rpc` is unreachable
+    #[arg(long, env, value_delimiter = ',')]
+    pub evm_fallback_ws_rpcs: Vec<String>,
+
     /// Bitcoin Core RPC URL with authentication (http(s)://username:password@host:port)
     #[arg(long, env)]
     pub btc_rpc: String,
@@ -83,6 +100,201 @@ pub struct HypernodeArgs {
         default_value = "prove-network"
     )]
     pub proof_generator: ProofGeneratorType,
+
+    /// Independent proof generator run alongside the primary one, swap proofs are only
+    /// broadcast when both produce the same public values (disabled if unset)
+    #[arg(long, env, value_parser = ProofGeneratorType::from_str)]
+    pub shadow_proof_generator: Option<ProofGeneratorType>,
+
+    /// Maximum attempts to re-establish a dropped Ethereum websocket connection (unbounded if unset)
+    #[arg(long, env)]
+    pub evm_ws_reconnect_max_attempts: Option<u32>,
+
+    /// Upper bound in seconds on the backoff between Ethereum websocket reconnection attempts
+    #[arg(long, env, default_value = "60")]
+    pub evm_ws_reconnect_max_backoff_secs: u64,
+
+    /// Expected chain ID of the Ethereum RPC, the hypernode refuses to start on any other chain
+    #[arg(long, env)]
+    pub evm_chain_id: Option<u64>,
+
+    /// Skip simulating swap proof transactions after this many consecutive successful broadcasts,
+    /// simulation resumes after any failure (always simulate if unset)
+    #[arg(long, env)]
+    pub skip_simulation_after_successes: Option<u32>,
+
+    /// Verify generated proofs locally before broadcasting, regenerating any that fail
+    #[arg(long, env)]
+    pub verify_proofs_locally: bool,
+
+    /// Times a failed call to the prover is retried, with exponential backoff, before the
+    /// proof counts as failed
+    #[arg(long, env, default_value = "2")]
+    pub prover_call_retries: u32,
+
+    /// Maximum calldata size in bytes of a swap proof transaction, larger transactions are
+    /// rejected before broadcast (defaults to geth's 128 KiB transaction size limit)
+    #[arg(long, env, default_value_t = txn_broadcast::DEFAULT_MAX_CALLDATA_BYTES)]
+    pub max_calldata_bytes: usize,
+
+    /// Esplora compatible block explorer API URL (e.g. https://blockstream.info/api) to
+    /// cross-check the local bitcoin tip against, alerting on divergence (disabled if unset)
+    #[arg(long, env)]
+    pub trusted_esplora_url: Option<String>,
+
+    /// Interval in seconds between checks of the local bitcoin tip against the trusted explorer
+    #[arg(long, env, default_value = "60")]
+    pub trusted_tip_check_interval_secs: u64,
+
+    /// Minimum seconds between repeated alerts for the same divergence from the trusted
+    /// explorer, a new divergence always alerts immediately
+    #[arg(long, env, default_value = "600")]
+    pub trusted_tip_alert_interval_secs: u64,
+
+    /// JSON file re-read on SIGHUP to retune the trusted tip check without a restart, e.g.
+    /// `{"check_interval_secs": 10, "alert_interval_secs": 60}`. Omitted settings are kept.
+    #[arg(long, env)]
+    pub trusted_tip_config_file: Option<PathBuf>,
+
+    /// Comma separated bitcoin block hashes (as displayed by block explorers) the light client
+    /// must never be updated to a chain containing, regardless of its work
+    #[arg(long, env, value_delimiter = ',')]
+    pub banned_block_hashes: Vec<String>,
+
+    /// Comma separated daily UTC windows (HH:MM-HH:MM) during which swap proofs are not
+    /// broadcast, e.g. for prover upgrades. Confirmed swaps are queued and finalized after.
+    #[arg(long, env, value_delimiter = ',')]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+
+    /// Seconds to wait after the local bitcoin chain reorgs the light client's tip before
+    /// building an update, so tips that reorg back within seconds aren't committed. Updates that
+    /// only extend the light client are built without waiting.
+    #[arg(long, env, default_value = "0")]
+    pub light_client_settle_delay_secs: u64,
+
+    /// Maximum swap proofs started per hour. Once reached, proving pauses with an error until
+    /// the hour is up; restart with a higher limit to override (unlimited if unset)
+    #[arg(long, env, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_proofs_per_hour: Option<u32>,
+}
+
+// Parses block hashes in display byte order into the natural byte order headers hash to
+fn parse_banned_block_hashes(banned_block_hashes: &[String]) -> Result<HashSet<[u8; 32]>> {
+    banned_block_hashes
+        .iter()
+        .map(|block_hash| -> Result<[u8; 32]> {
+            let mut block_hash: [u8; 32] = hex::decode(block_hash.trim_start_matches("0x"))?
+                .try_into()
+                .map_err(|_| eyre::eyre!("Banned block hash {} is not 32 bytes", block_hash))?;
+            block_hash.reverse();
+            Ok(block_hash)
+        })
+        .collect()
+}
+
+// The chain ID lookup made by the startup check
+trait EvmChainIdSource {
+    fn get_chain_id(&self) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl EvmChainIdSource for WebsocketWalletProvider {
+    async fn get_chain_id(&self) -> Result<u64> {
+        Ok(Provider::get_chain_id(self).await?)
+    }
+}
+
+// Guards against pointing the hypernode at the wrong network, where it would spend real funds.
+// Skipped when no chain ID is expected.
+async fn check_evm_chain_id(
+    evm_rpc: &impl EvmChainIdSource,
+    expected_chain_id: Option<u64>,
+) -> Result<()> {
+    let Some(expected_chain_id) = expected_chain_id else {
+        return Ok(());
+    };
+    let connected_chain_id = evm_rpc.get_chain_id().await?;
+    if connected_chain_id != expected_chain_id {
+        return Err(eyre::eyre!(
+            "Connected to EVM chain ID {}, expected {}",
+            connected_chain_id,
+            expected_chain_id
+        ));
+    }
+    info!(chain_id = expected_chain_id, "Verified EVM chain ID");
+    Ok(())
+}
+
+// A verification key mismatch makes every proof revert onchain, which would otherwise look like
+// a transient proof failure
+fn check_circuit_verification_key(
+    onchain_verification_key: [u8; 32],
+    prover_verification_key: [u8; 32],
+) -> Result<()> {
+    if onchain_verification_key != prover_verification_key {
+        return Err(eyre::eyre!(
+            "RiftExchange expects circuit verification key 0x{}, but the proof generator produces proofs for 0x{}",
+            hex::encode(onchain_verification_key),
+            hex::encode(prover_verification_key)
+        ));
+    }
+    Ok(())
+}
+
+// The light client MMR is only ever seeded from the checkpoint file, if it has no leaves the
+// hypernode would sit idle with no light client tip to prove swaps against
+async fn ensure_light_client_initialized(
+    contract_data_engine: &data_engine::engine::ContractDataEngine,
+) -> Result<()> {
+    if contract_data_engine.get_leaf_count().await? == 0 {
+        return Err(eyre::eyre!(
+            "Light client MMR is uninitialized, the checkpoint file must contain at least one block"
+        ));
+    }
+    Ok(())
+}
+
+// The light client's first block (genesis, unless the checkpoint starts later) must be the block
+// the bitcoin data engine has at the same height, otherwise the contract and the bitcoin node are
+// on different bitcoin networks and nothing the hypernode proves would be valid
+fn check_same_bitcoin_network(
+    light_client_leaf: &BlockLeaf,
+    bitcoin_leaf: Option<&BlockLeaf>,
+) -> Result<()> {
+    let display_hash = |leaf: &BlockLeaf| hex::encode(leaf.block_hash);
+    match bitcoin_leaf {
+        Some(bitcoin_leaf) if bitcoin_leaf == light_client_leaf => Ok(()),
+        Some(bitcoin_leaf) => Err(eyre::eyre!(
+            "Bitcoin network mismatch at height {}: light client has block {}, bitcoin node has block {}",
+            light_client_leaf.height,
+            display_hash(light_client_leaf),
+            display_hash(bitcoin_leaf)
+        )),
+        None => Err(eyre::eyre!(
+            "Bitcoin node has no block at height {} to compare with light client block {}",
+            light_client_leaf.height,
+            display_hash(light_client_leaf)
+        )),
+    }
+}
+
+async fn ensure_same_bitcoin_network(
+    contract_data_engine: &data_engine::engine::ContractDataEngine,
+    bitcoin_data_engine: &bitcoin_data_engine::BitcoinDataEngine,
+) -> Result<()> {
+    let light_client_leaf = contract_data_engine
+        .checkpointed_block_tree
+        .read()
+        .await
+        .get_leaf_by_leaf_index(0)
+        .await?
+        .ok_or_else(|| eyre::eyre!("Light client MMR is uninitialized"))?;
+    let bitcoin_leaf = bitcoin_data_engine
+        .indexed_mmr
+        .read()
+        .await
+        .get_leaf_by_leaf_index(light_client_leaf.height as usize)
+        .await?;
+    check_same_bitcoin_network(&light_client_leaf, bitcoin_leaf.as_ref())
 }
 
 const BITCOIN_RPC_TIMEOUT: Duration = Duration::from_secs(1);
@@ -90,6 +302,7 @@ const BITCOIN_BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub async fn run(args: HypernodeArgs) -> Result<()> {
     let rift_exchange_address = Address::from_str(&args.rift_exchange_address)?;
+    let banned_block_hashes = parse_banned_block_hashes(&args.banned_block_hashes)?;
 
     let checkpoint_leaves = decompress_checkpoint_file(&args.checkpoint_file)?;
     info!(
@@ -98,16 +311,36 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
     );
 
     // [1] create rpc providers for both chains
+    let private_key: [u8; 32] = hex::decode(&args.private_key)
+        .map_err(|e| eyre::eyre!(e))?
+        .try_into()
+        .map_err(|_| eyre::eyre!("Invalid private key length"))?;
+    let evm_ws_reconnect_policy = WsReconnectPolicy {
+        max_attempts: args.evm_ws_reconnect_max_attempts,
+        max_interval: Duration::from_secs(args.evm_ws_reconnect_max_backoff_secs),
+        ..Default::default()
+    };
     let evm_rpc = Arc::new(
-        create_websocket_wallet_provider(
+        create_websocket_wallet_provider_with_reconnect_policy(
             &args.evm_ws_rpc,
-            hex::decode(&args.private_key)
-                .map_err(|e| eyre::eyre!(e))?
-                .try_into()
-                .map_err(|_| eyre::eyre!("Invalid private key length"))?,
+            private_key,
+            evm_ws_reconnect_policy.clone(),
         )
         .await?,
     );
+    let mut fallback_evm_rpcs = Vec::with_capacity(args.evm_fallback_ws_rpcs.len());
+    for evm_fallback_ws_rpc in &args.evm_fallback_ws_rpcs {
+        fallback_evm_rpcs.push(Arc::new(
+            create_websocket_wallet_provider_with_reconnect_policy(
+                evm_fallback_ws_rpc,
+                private_key,
+                evm_ws_reconnect_policy.clone(),
+            )
+            .await?,
+        ));
+    }
+
+    check_evm_chain_id(evm_rpc.as_ref(), args.evm_chain_id).await?;
 
     let btc_rpc = Arc::new(
         rift_sdk::bitcoin_utils::AsyncBitcoinClient::new(
@@ -128,6 +361,14 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
         info!("Starting proof generator initialization");
         Arc::new(RiftProofGenerator::new(args.proof_generator))
     });
+    let shadow_proof_generator_handle = tokio::task::spawn_blocking(move || {
+        args.shadow_proof_generator.map(|generator_type| {
+            let _span = info_span!("shadow_proof_generator_init", generator_type = ?generator_type)
+                .entered();
+            info!("Starting shadow proof generator initialization");
+            Arc::new(RiftProofGenerator::new(generator_type))
+        })
+    });
 
     let contract_data_engine = {
         info!("Starting contract data engine initialization");
@@ -140,6 +381,7 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
             &mut join_set,
         )
         .await?;
+        ensure_light_client_initialized(&engine).await?;
         // Handle the contract data engine background thread crashing before the initial sync completes
         tokio::select! {
             _ = engine.wait_for_initial_sync() => {
@@ -174,15 +416,76 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
         Arc::new(engine)
     };
 
-    let transaction_broadcaster = Arc::new(TransactionBroadcaster::new(
+    ensure_same_bitcoin_network(&contract_data_engine, &bitcoin_data_engine).await?;
+    info!("Verified light client and bitcoin node are on the same bitcoin network");
+
+    if let Some(trusted_esplora_url) = &args.trusted_esplora_url {
+        let (tip_oracle_config_tx, tip_oracle_config_rx) = watch::channel(TipOracleConfig {
+            check_interval: Duration::from_secs(args.trusted_tip_check_interval_secs),
+            alert_interval: Duration::from_secs(args.trusted_tip_alert_interval_secs),
+        });
+        join_set.spawn(
+            tip_oracle::run_tip_oracle_check(
+                EsploraTipOracle::new(trusted_esplora_url),
+                bitcoin_data_engine.clone(),
+                tip_oracle_config_rx,
+            )
+            .instrument(info_span!("Trusted Tip Oracle")),
+        );
+        // without a config file the sender is dropped and the config stays fixed
+        if let Some(trusted_tip_config_file) = &args.trusted_tip_config_file {
+            join_set.spawn(
+                tip_oracle::reload_tip_oracle_config_on_sighup(
+                    trusted_tip_config_file.clone(),
+                    tip_oracle_config_tx,
+                )
+                .instrument(info_span!("Trusted Tip Config Reload")),
+            );
+        }
+    }
+
+    let transaction_broadcaster = Arc::new(TransactionBroadcaster::with_fallback_rpcs(
         evm_rpc.clone(),
+        fallback_evm_rpcs,
         args.evm_ws_rpc.clone(),
         &mut join_set,
     ));
 
     let proof_generator = proof_generator_handle.await?;
+    let shadow_proof_generator = shadow_proof_generator_handle.await?;
+
+    let onchain_verification_key =
+        sol_bindings::RiftExchange::new(rift_exchange_address, evm_rpc.clone())
+            .CIRCUIT_VERIFICATION_KEY()
+            .call()
+            .await?
+            ._0;
+    check_circuit_verification_key(
+        onchain_verification_key.0,
+        proof_generator.circuit_verification_key_hash,
+    )?;
+    if let Some(shadow_proof_generator) = &shadow_proof_generator {
+        check_circuit_verification_key(
+            onchain_verification_key.0,
+            shadow_proof_generator.circuit_verification_key_hash,
+        )?;
+    }
+    info!(
+        circuit_verification_key = %onchain_verification_key,
+        "Verified circuit verification key"
+    );
 
     info!("Starting hypernode watchtowers...");
+    let swap_watchtower_config = SwapWatchtowerConfig {
+        skip_simulation_after_successes: args.skip_simulation_after_successes,
+        verify_proofs_locally: args.verify_proofs_locally,
+        prover_call_retries: args.prover_call_retries,
+        max_calldata_bytes: args.max_calldata_bytes,
+        banned_block_hashes,
+        maintenance_windows: args.maintenance_windows.clone(),
+        light_client_settle_delay: Duration::from_secs(args.light_client_settle_delay_secs),
+        max_proofs_per_hour: args.max_proofs_per_hour,
+    };
     SwapWatchtower::run(
         contract_data_engine.clone(),
         bitcoin_data_engine.clone(),
@@ -192,6 +495,8 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
         transaction_broadcaster.clone(),
         args.btc_batch_rpc_size,
         proof_generator,
+        shadow_proof_generator,
+        swap_watchtower_config,
         &mut join_set,
     );
 
@@ -207,3 +512,120 @@ pub async fn run(args: HypernodeArgs) -> Result<()> {
     // Wait for one of the background threads to complete or fail. (Ideally never happens, but we want to crash the program if it does)
     handle_background_thread_result(join_set.join_next().await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ensure_light_client_initialized() {
+        let empty_engine =
+            data_engine::engine::ContractDataEngine::seed(&DatabaseLocation::InMemory, vec![])
+                .await
+                .unwrap();
+        let err = ensure_light_client_initialized(&empty_engine)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Light client MMR is uninitialized, the checkpoint file must contain at least one block"
+        );
+
+        let seeded_engine = data_engine::engine::ContractDataEngine::seed(
+            &DatabaseLocation::InMemory,
+            vec![bitcoin_light_client_core::leaves::get_genesis_leaf()],
+        )
+        .await
+        .unwrap();
+        assert!(ensure_light_client_initialized(&seeded_engine)
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_check_same_bitcoin_network() {
+        let mainnet_genesis = bitcoin_light_client_core::leaves::get_genesis_leaf();
+        let testnet_genesis = BlockLeaf::new(
+            hex::decode("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+                .unwrap()
+                .try_into()
+                .unwrap(),
+            0,
+            mainnet_genesis.cumulative_chainwork,
+        );
+
+        assert!(check_same_bitcoin_network(&mainnet_genesis, Some(&mainnet_genesis)).is_ok());
+
+        let err = check_same_bitcoin_network(&mainnet_genesis, Some(&testnet_genesis)).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Bitcoin network mismatch at height 0"));
+
+        assert!(check_same_bitcoin_network(&mainnet_genesis, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_banned_block_hashes() {
+        let banned_block_hashes = parse_banned_block_hashes(&[
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f".to_string(),
+        ])
+        .unwrap();
+
+        let genesis_hash =
+            bitcoin_light_client_core::leaves::get_genesis_leaf().natural_block_hash();
+        assert_eq!(banned_block_hashes, HashSet::from([genesis_hash]));
+
+        assert!(parse_banned_block_hashes(&["00".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_check_circuit_verification_key() {
+        assert!(check_circuit_verification_key([1; 32], [1; 32]).is_ok());
+
+        let err = check_circuit_verification_key([1; 32], [2; 32]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "RiftExchange expects circuit verification key 0x{}, but the proof generator produces proofs for 0x{}",
+                hex::encode([1u8; 32]),
+                hex::encode([2u8; 32])
+            )
+        );
+    }
+
+    // Connected to the chain with the given ID, or unreachable if `None`
+    struct StubChainIdSource(Option<u64>);
+
+    impl EvmChainIdSource for StubChainIdSource {
+        async fn get_chain_id(&self) -> Result<u64> {
+            self.0.ok_or_else(|| eyre::eyre!("EVM rpc unreachable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_evm_chain_id() {
+        assert!(check_evm_chain_id(&StubChainIdSource(Some(1)), Some(1))
+            .await
+            .is_ok());
+
+        // startup fails on the wrong network
+        let err = check_evm_chain_id(&StubChainIdSource(Some(11155111)), Some(1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Connected to EVM chain ID 11155111, expected 1"
+        );
+
+        // and when the chain ID can't be checked
+        let err = check_evm_chain_id(&StubChainIdSource(None), Some(1))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "EVM rpc unreachable");
+
+        // no expected chain ID, no lookup
+        assert!(check_evm_chain_id(&StubChainIdSource(None), None)
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::info;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// A daily UTC time range, written `HH:MM-HH:MM`, during which the hypernode keeps watching
+/// for swaps but defers broadcasting proofs. A range whose end is before its start wraps past
+/// midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    start_secs: u32,
+    end_secs: u32,
+}
+
+impl MaintenanceWindow {
+    /// Time left in the window at `secs_of_day` seconds past UTC midnight, `None` if outside it
+    pub fn remaining_at(&self, secs_of_day: u32) -> Option<Duration> {
+        let remaining = if self.start_secs <= self.end_secs {
+            (self.start_secs..self.end_secs)
+                .contains(&secs_of_day)
+                .then(|| self.end_secs - secs_of_day)
+        } else if secs_of_day >= self.start_secs {
+            Some(SECONDS_PER_DAY - secs_of_day + self.end_secs)
+        } else if secs_of_day < self.end_secs {
+            Some(self.end_secs - secs_of_day)
+        } else {
+            None
+        };
+        remaining.map(|secs| Duration::from_secs(secs as u64))
+    }
+}
+
+fn parse_time_of_day(time: &str) -> Result<u32, String> {
+    let (hours, minutes) = time
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid time {}, expected HH:MM", time))?;
+    let hours: u32 = hours
+        .parse()
+        .map_err(|_| format!("Invalid hours in {}", time))?;
+    let minutes: u32 = minutes
+        .parse()
+        .map_err(|_| format!("Invalid minutes in {}", time))?;
+    if hours > 23 || minutes > 59 {
+        return Err(format!("Time {} is out of range", time));
+    }
+    Ok(hours * 3600 + minutes * 60)
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = String;
+
+    fn from_str(window: &str) -> Result<Self, Self::Err> {
+        let (start, end) = window.trim().split_once('-').ok_or_else(|| {
+            format!(
+                "Invalid maintenance window {}, expected HH:MM-HH:MM",
+                window
+            )
+        })?;
+        let start_secs = parse_time_of_day(start)?;
+        let end_secs = parse_time_of_day(end)?;
+        if start_secs == end_secs {
+            return Err(format!("Maintenance window {} is empty", window));
+        }
+        Ok(Self {
+            start_secs,
+            end_secs,
+        })
+    }
+}
+
+/// Longest time left across the windows active at `secs_of_day`, `None` if none are active
+pub fn active_maintenance_window(
+    windows: &[MaintenanceWindow],
+    secs_of_day: u32,
+) -> Option<Duration> {
+    windows
+        .iter()
+        .filter_map(|window| window.remaining_at(secs_of_day))
+        .max()
+}
+
+fn current_secs_of_day() -> u32 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time is before the unix epoch");
+    (now.as_secs() % SECONDS_PER_DAY as u64) as u32
+}
+
+/// Returns once no maintenance window is active, sleeping through any that are
+pub async fn wait_for_maintenance_windows(windows: &[MaintenanceWindow]) {
+    while let Some(remaining) = active_maintenance_window(windows, current_secs_of_day()) {
+        info!(
+            message = "In maintenance window, deferring swap finalization",
+            remaining_secs = remaining.as_secs()
+        );
+        tokio::time::sleep(remaining).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_maintenance_window() {
+        assert_eq!(
+            "02:00-03:30".parse::<MaintenanceWindow>(),
+            Ok(MaintenanceWindow {
+                start_secs: 2 * 3600,
+                end_secs: 3 * 3600 + 30 * 60,
+            })
+        );
+        assert!("02:00".parse::<MaintenanceWindow>().is_err());
+        assert!("24:00-01:00".parse::<MaintenanceWindow>().is_err());
+        assert!("01:00-01:00".parse::<MaintenanceWindow>().is_err());
+    }
+
+    #[test]
+    fn test_finalization_deferred_inside_window_and_resumed_after() {
+        let windows: Vec<MaintenanceWindow> = vec![
+            "02:00-03:00".parse().unwrap(),
+            "23:30-00:15".parse().unwrap(),
+        ];
+
+        // inside the windows, deferred until they close
+        assert_eq!(
+            active_maintenance_window(&windows, 2 * 3600 + 45 * 60),
+            Some(Duration::from_secs(15 * 60))
+        );
+        assert_eq!(
+            active_maintenance_window(&windows, 23 * 3600 + 45 * 60),
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            active_maintenance_window(&windows, 10 * 60),
+            Some(Duration::from_secs(5 * 60))
+        );
+
+        // resumed once they close
+        assert_eq!(active_maintenance_window(&windows, 3 * 3600), None);
+        assert_eq!(active_maintenance_window(&windows, 15 * 60), None);
+        assert_eq!(active_maintenance_window(&windows, 12 * 3600), None);
+        assert_eq!(active_maintenance_window(&[], 2 * 3600), None);
+    }
+}
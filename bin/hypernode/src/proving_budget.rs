@@ -0,0 +1,166 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tracing::error;
+
+/// Length of the fixed window `--max-proofs-per-hour` is counted over
+pub const PROVING_BUDGET_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Caps the number of prover calls started per window, a financial safety limit against a bug or
+/// attack driving repeated proving. Every call counts, retries and shadow proofs included. A
+/// window starts with the first call after the previous one ended. `None` never pauses.
+#[derive(Debug, Clone)]
+pub struct ProvingBudget {
+    max_proofs: Option<u32>,
+    window: Duration,
+    window_start: Option<Instant>,
+    proofs_in_window: u32,
+}
+
+impl ProvingBudget {
+    pub fn new(max_proofs: Option<u32>, window: Duration) -> Self {
+        Self {
+            max_proofs,
+            window,
+            window_start: None,
+            proofs_in_window: 0,
+        }
+    }
+
+    /// Time until proving may resume at `now`, `None` if another proof fits in the budget
+    pub fn paused_for(&mut self, now: Instant) -> Option<Duration> {
+        let max_proofs = self.max_proofs?;
+        let window_start = self.window_start?;
+        let elapsed = now.duration_since(window_start);
+        if elapsed >= self.window {
+            self.window_start = None;
+            self.proofs_in_window = 0;
+            return None;
+        }
+        (self.proofs_in_window >= max_proofs).then(|| self.window - elapsed)
+    }
+
+    pub fn record_proof(&mut self, now: Instant) {
+        if self.max_proofs.is_none() {
+            return;
+        }
+        self.window_start.get_or_insert(now);
+        self.proofs_in_window += 1;
+    }
+
+    /// Records a proof started at `now` if it fits in the budget, otherwise returns the time until
+    /// proving may resume
+    pub fn try_record_proof(&mut self, now: Instant) -> Option<Duration> {
+        let paused_for = self.paused_for(now);
+        if paused_for.is_none() {
+            self.record_proof(now);
+        }
+        paused_for
+    }
+}
+
+fn log_proving_paused(max_proofs: Option<u32>, remaining: Duration) {
+    error!(
+        message = "Proving budget exhausted, pausing all proving until the window ends",
+        max_proofs,
+        remaining_secs = remaining.as_secs()
+    );
+}
+
+/// Returns once `budget` allows another proof, sleeping until the window ends if it's exhausted.
+/// Restarting the hypernode with a higher or unset limit overrides the pause.
+pub async fn wait_for_proving_budget(budget: &Mutex<ProvingBudget>) {
+    loop {
+        let (max_proofs, paused_for) = {
+            let mut budget = budget.lock().unwrap();
+            (budget.max_proofs, budget.paused_for(Instant::now()))
+        };
+        let Some(remaining) = paused_for else {
+            return;
+        };
+        log_proving_paused(max_proofs, remaining);
+        tokio::time::sleep(remaining).await;
+    }
+}
+
+/// Like `wait_for_proving_budget`, additionally recording the prover call about to be made.
+/// Called before every prover call, the batch level wait alone would let retries and shadow
+/// proofs run past the budget.
+pub async fn start_prover_call(budget: &Mutex<ProvingBudget>) {
+    loop {
+        let (max_proofs, paused_for) = {
+            let mut budget = budget.lock().unwrap();
+            (budget.max_proofs, budget.try_record_proof(Instant::now()))
+        };
+        let Some(remaining) = paused_for else {
+            return;
+        };
+        log_proving_paused(max_proofs, remaining);
+        tokio::time::sleep(remaining).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exhausted_budget_pauses_proving_until_window_ends() {
+        let mut budget = ProvingBudget::new(Some(3), PROVING_BUDGET_WINDOW);
+        let start = Instant::now();
+
+        for minute in 0..3 {
+            let now = start + Duration::from_secs(minute * 60);
+            assert_eq!(budget.paused_for(now), None);
+            budget.record_proof(now);
+        }
+
+        // exhausted, paused for the rest of the window
+        assert_eq!(
+            budget.paused_for(start + Duration::from_secs(10 * 60)),
+            Some(Duration::from_secs(50 * 60))
+        );
+        assert_eq!(
+            budget.paused_for(start + Duration::from_secs(59 * 60)),
+            Some(Duration::from_secs(60))
+        );
+
+        // resumed once the window ends, with a fresh budget
+        let next_window = start + PROVING_BUDGET_WINDOW;
+        assert_eq!(budget.paused_for(next_window), None);
+        for _ in 0..3 {
+            budget.record_proof(next_window);
+        }
+        assert!(budget.paused_for(next_window).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_prover_calls_recorded_until_budget_exhausted() {
+        let budget = Mutex::new(ProvingBudget::new(Some(2), PROVING_BUDGET_WINDOW));
+        wait_for_proving_budget(&budget).await;
+        // waiting alone doesn't use up the budget
+        assert_eq!(budget.lock().unwrap().proofs_in_window, 0);
+
+        start_prover_call(&budget).await;
+        start_prover_call(&budget).await;
+        assert_eq!(budget.lock().unwrap().proofs_in_window, 2);
+        assert!(budget
+            .lock()
+            .unwrap()
+            .try_record_proof(Instant::now())
+            .is_some());
+        assert_eq!(budget.lock().unwrap().proofs_in_window, 2);
+    }
+
+    #[test]
+    fn test_unlimited_budget_never_pauses() {
+        let mut budget = ProvingBudget::new(None, PROVING_BUDGET_WINDOW);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            budget.record_proof(now);
+        }
+        assert_eq!(budget.paused_for(now), None);
+    }
+}
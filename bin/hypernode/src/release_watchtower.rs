@@ -29,16 +29,29 @@ use tracing::{info, info_span, Instrument};
 
 use crate::txn_broadcast::{PreflightCheck, TransactionBroadcaster};
 
-async fn pump_blocks_into_watch(
-    mut block_stream: impl Stream<Item = Header> + Unpin,
-    tx: watch::Sender<Option<Header>>,
+async fn pump_blocks_into_watch<T>(
+    mut block_stream: impl Stream<Item = T> + Unpin,
+    tx: watch::Sender<Option<T>>,
 ) -> eyre::Result<()> {
     while let Some(block) = block_stream.next().await {
         // Sending overwrites any previous block
         let _ = tx.send(Some(block));
     }
-    // Should never return, just to make all long running tasks return a result
-    Ok(())
+    // The block subscription ended, release detection would silently stop without new blocks
+    Err(eyre::eyre!("EVM block subscription ended"))
+}
+
+/// Waits for the next block pumped into the watch, erroring once the sender is dropped since
+/// no further blocks can arrive
+async fn next_block_header<T: Clone>(
+    rx: &mut watch::Receiver<Option<T>>,
+) -> eyre::Result<Option<T>> {
+    rx.changed()
+        .await
+        .map_err(|_| eyre::eyre!("EVM block watch channel closed"))?;
+    // Borrow the newest header, clone it, and drop the borrow immediately.
+    let latest = rx.borrow().clone();
+    Ok(latest)
 }
 
 pub struct ReleaseWatchtower;
@@ -103,11 +116,8 @@ async fn search_on_new_evm_blocks(
 ) -> eyre::Result<()> {
     let rift_exchange = RiftExchange::new(rift_exchange_address, evm_rpc);
     // Consume blocks from the watch
-    while rx.changed().await.is_ok() {
-        // Borrow the newest header, clone it, and drop the borrow immediately.
-        let maybe_latest = rx.borrow().clone();
-
-        if let Some(latest_block_header) = maybe_latest {
+    loop {
+        if let Some(latest_block_header) = next_block_header(&mut rx).await? {
             search_for_releases(
                 &rift_exchange,
                 transaction_broadcaster.clone(),
@@ -117,8 +127,6 @@ async fn search_on_new_evm_blocks(
             .await?;
         }
     }
-
-    Ok(())
 }
 async fn search_for_releases(
     rift_exchange: &RiftExchangeClient,
@@ -211,3 +219,30 @@ async fn search_for_releases(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_next_block_header_errors_when_sender_dropped() {
+        let (tx, mut rx) = watch::channel(None::<u64>);
+
+        tx.send(Some(1u64)).unwrap();
+        assert_eq!(next_block_header(&mut rx).await.unwrap(), Some(1));
+
+        drop(tx);
+        let err = next_block_header(&mut rx).await.unwrap_err();
+        assert_eq!(err.to_string(), "EVM block watch channel closed");
+    }
+
+    #[tokio::test]
+    async fn test_pump_blocks_into_watch_errors_when_stream_ends() {
+        let (tx, rx) = watch::channel(None);
+
+        let result = pump_blocks_into_watch(stream::iter(vec![1u64, 2]), tx).await;
+
+        assert!(result.is_err());
+        assert_eq!(*rx.borrow(), Some(2));
+    }
+}
@@ -1,13 +1,20 @@
 use accumulators::mmr::map_leaf_index_to_element_index;
 use alloy::{
-    eips::eip7251::ConsolidationRequest, primitives::Address, providers::Provider,
-    pubsub::PubSubFrontend, sol_types::SolValue,
+    eips::eip7251::ConsolidationRequest,
+    primitives::{utils::format_ether, Address},
+    providers::Provider,
+    pubsub::PubSubFrontend,
+    rpc::types::TransactionReceipt,
+    sol_types::{SolEvent, SolValue},
 };
 use bitcoin::{block::Version, consensus::Decodable, CompactTarget};
 use bitcoin_data_engine::BitcoinDataEngine;
 use bitcoin_light_client_core::{
-    hasher::Keccak256Hasher, leaves::BlockLeaf, light_client::Header, ChainTransition, ProvenLeaf,
-    VerifiedBlock,
+    hasher::{Digest, Hasher, Keccak256Hasher},
+    leaves::{validate_tip_extension, BlockLeaf, BlockLeafCompressor},
+    light_client::{check_banned_blocks, find_divergence, Header},
+    mmr::compute_root_after_append,
+    ChainTransition, ProvenLeaf, VerifiedBlock,
 };
 use bitcoincore_rpc_async::{
     bitcoin::{block::Header as BlockHeader, hashes::Hash, Block, BlockHash, Txid},
@@ -25,8 +32,8 @@ use rift_sdk::{
     bitcoin_utils::{AsyncBitcoinClient, BitcoinClientExt},
     checkpoint_mmr::CheckpointedBlockTree,
     get_retarget_height_from_block_height,
-    indexed_mmr::IndexedMMR,
-    proof_generator::RiftProofGenerator,
+    indexed_mmr::{leaf_count_to_elements_count, IndexedMMR, MmrRoot},
+    proof_generator::{format_duration, Proof, RiftProofGenerator},
     txn_builder::serialize_no_segwit,
     WebsocketWalletProvider,
 };
@@ -34,18 +41,54 @@ use sol_bindings::{
     RiftExchange::{self, RiftExchangeInstance},
     Types::{BlockProofParams, DepositVault, SubmitSwapProofParams},
 };
-use std::sync::Arc;
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::{
     sync::{
         mpsc::{UnboundedReceiver, UnboundedSender},
-        RwLockReadGuard,
+        RwLock, RwLockReadGuard,
     },
     task::{JoinHandle, JoinSet},
 };
 use tokio_util::task::TaskTracker;
-use tracing::{info, info_span, instrument, warn, Instrument};
+use tracing::{error, info, info_span, instrument, warn, Instrument};
+
+use crate::maintenance::{wait_for_maintenance_windows, MaintenanceWindow};
+use crate::proving_budget::{
+    start_prover_call, wait_for_proving_budget, ProvingBudget, PROVING_BUDGET_WINDOW,
+};
+use crate::txn_broadcast::{
+    check_calldata_size, transaction_cost_wei, AdaptivePreflight, TransactionBroadcaster,
+    TransactionExecutionResult,
+};
 
-use crate::txn_broadcast::{PreflightCheck, TransactionBroadcaster};
+// How often a log line is emitted while a proof is being generated, so a slow proof can be
+// told apart from a hung one
+const PROOF_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// How many proofs are generated for a batch before giving up on local verification failures
+const PROOF_GENERATION_ATTEMPTS: usize = 2;
+// Delay before the first retry of a failed prover call, doubled on each retry after
+const PROVER_CALL_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+// How often an unreachable prover is probed for reachability, the held batch is proven again
+// once it's back
+const PROVER_UNAVAILABLE_BACKOFF: Duration = Duration::from_secs(60);
+// Bounds the rpc calls made before the swap search loop starts, so a hung rpc at startup is
+// logged and retried instead of silently wedging the watchtower
+const STARTUP_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+const STARTUP_RPC_ATTEMPTS: usize = 5;
+// How long to let the contract data engine catch up to the on-chain light client before
+// rebuilding a transition whose prior root no longer matches the contract
+const PRIOR_ROOT_MISMATCH_REBUILD_DELAY: Duration = Duration::from_secs(5);
+// How many times in a row a batch is rebuilt on a mismatched prior root before giving up, the
+// contract data engine is stuck rather than lagging if it hasn't caught up by then
+const MAX_PRIOR_ROOT_REBUILDS: u32 = 12;
+// How long a batch is held when its light client update would commit a banned block, giving the
+// local chain time to reorg away from the block before the update is rebuilt
+const BANNED_BLOCK_HOLD_DELAY: Duration = Duration::from_secs(60);
 
 struct PendingSwap {
     chain_aware_deposit: ChainAwareDeposit,
@@ -59,6 +102,28 @@ struct ConfirmedSwap {
     rift_transaction_input: RiftTransaction,
 }
 
+/// Settings of the confirmed swap finalizer, built once from the hypernode's args
+#[derive(Debug, Clone)]
+pub struct SwapWatchtowerConfig {
+    /// Stop simulating swap proof transactions after this many successful broadcasts in a row,
+    /// `None` always simulates
+    pub skip_simulation_after_successes: Option<u32>,
+    /// Verify generated proofs before broadcasting them
+    pub verify_proofs_locally: bool,
+    /// Retries of a failed prover call before the batch fails
+    pub prover_call_retries: u32,
+    /// Swap proof transactions with larger calldata are rejected before broadcast
+    pub max_calldata_bytes: usize,
+    /// Blocks the light client must never be updated to a chain containing
+    pub banned_block_hashes: HashSet<[u8; 32]>,
+    /// Windows in which proofs aren't generated or broadcast
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// How long a reorg of the light client's tip settles before an update is built
+    pub light_client_settle_delay: Duration,
+    /// Cap on prover calls per hour, `None` is unlimited
+    pub max_proofs_per_hour: Option<u32>,
+}
+
 pub struct SwapWatchtower;
 
 impl SwapWatchtower {
@@ -71,6 +136,8 @@ impl SwapWatchtower {
         transaction_broadcaster: Arc<TransactionBroadcaster>,
         bitcoin_concurrency_limit: usize,
         proof_generator: Arc<RiftProofGenerator>,
+        shadow_proof_generator: Option<Arc<RiftProofGenerator>>,
+        config: SwapWatchtowerConfig,
         join_set: &mut JoinSet<eyre::Result<()>>,
     ) {
         let (confirmed_swaps_tx, confirmed_swaps_rx) =
@@ -111,9 +178,11 @@ impl SwapWatchtower {
                     contract_data_engine_clone,
                     bitcoin_concurrency_limit,
                     proof_generator_clone,
+                    shadow_proof_generator,
                     rift_exchange_address,
                     evm_rpc_clone,
                     transaction_broadcaster_clone,
+                    config,
                 )
                 .await
             }
@@ -135,11 +204,18 @@ impl SwapWatchtower {
         info!("Subscribed to new bitcoin blocks");
 
         let (start_search_bitcoin_block_number, end_search_bitcoin_block_number) =
-            compute_block_search_range(
-                evm_rpc,
-                btc_rpc.clone(),
-                contract_data_engine.clone(),
-                bitcoin_data_engine.clone(),
+            with_startup_timeout(
+                "compute_block_search_range",
+                STARTUP_RPC_TIMEOUT,
+                STARTUP_RPC_ATTEMPTS,
+                || {
+                    compute_block_search_range(
+                        evm_rpc.clone(),
+                        btc_rpc.clone(),
+                        contract_data_engine.clone(),
+                        bitcoin_data_engine.clone(),
+                    )
+                },
             )
             .await?;
 
@@ -237,15 +313,30 @@ impl SwapWatchtower {
         contract_data_engine: Arc<ContractDataEngine>,
         bitcoin_concurrency_limit: usize,
         proof_generator: Arc<RiftProofGenerator>,
+        shadow_proof_generator: Option<Arc<RiftProofGenerator>>,
         evm_address: Address,
         evm_rpc: Arc<WebsocketWalletProvider>,
         transaction_broadcaster: Arc<TransactionBroadcaster>,
+        config: SwapWatchtowerConfig,
     ) -> eyre::Result<()> {
         let rift_exchange = RiftExchange::new(evm_address, evm_rpc);
+        let mut preflight = AdaptivePreflight::new(config.skip_simulation_after_successes);
+        // shared by the primary and shadow prover calls, which run concurrently
+        let proving_budget = Mutex::new(ProvingBudget::new(
+            config.max_proofs_per_hour,
+            PROVING_BUDGET_WINDOW,
+        ));
+        // swaps held back from a previous batch, finalized again first
+        let mut rebuild_swaps = None;
+        let mut prior_root_rebuilds = PriorRootRebuilds::new(MAX_PRIOR_ROOT_REBUILDS);
         loop {
-            let mut confirmed_swaps = confirmed_swaps_rx.recv().await.ok_or_else(|| {
-                eyre::eyre!("Confirmed swaps channel receiver unexpectedly closed")
-            })?;
+            let mut confirmed_swaps =
+                next_confirmed_swaps(&mut rebuild_swaps, &mut confirmed_swaps_rx).await?;
+
+            // swaps confirmed during the window queue up in the channel and are drained below
+            wait_for_maintenance_windows(&config.maintenance_windows).await;
+            // likewise while proving is paused by the budget
+            wait_for_proving_budget(&proving_budget).await;
 
             loop {
                 // drain the channel of any additional confirmed swaps to handle in one batch
@@ -264,32 +355,101 @@ impl SwapWatchtower {
             // 1. Determine what the state of the onchain light client is (current tip)
             // 2. If it's equal to the locally stored chain, do nothing
 
-            // lock both the light client and bitcoin core mmrs while we finalize the swaps
-            let light_client_mmr = contract_data_engine.checkpointed_block_tree.read().await;
+            // new blocks on top of the light client's tip are proven right away, only a reorg of
+            // its tip waits for the local chain to settle
+            if !config.light_client_settle_delay.is_zero() {
+                settle_light_client_reorg(config.light_client_settle_delay, || async {
+                    light_client_tip_reorged(
+                        &*bitcoin_data_engine.indexed_mmr.read().await,
+                        &*contract_data_engine.checkpointed_block_tree.read().await,
+                    )
+                    .await
+                })
+                .await?;
+            }
+
+            // lock the bitcoin core mmr while we finalize the swaps, the light client mmr is
+            // only locked while its state is read so contract data engine writers aren't blocked
             let bitcoin_mmr = bitcoin_data_engine.indexed_mmr.read().await;
-            let btc_light_client_root = light_client_mmr.get_root().await?;
-            let btc_local_root = bitcoin_mmr.get_root().await?;
+            let btc_light_client_root = contract_data_engine.get_mmr_root().await?;
+            let btc_local_root = MmrRoot::from(bitcoin_mmr.get_root().await?);
             info!(message = "Starting finalize_confirmed_swaps");
 
             let mut light_client_update = false;
+            let mut expected_light_client_update = None;
             let mut rift_program_input_builder = RiftProgramInput::builder();
             if btc_light_client_root != btc_local_root {
-                let light_client_span =
-                    info_span!("light_client_update", operation = "build_transition");
-                let _enter = light_client_span.enter();
+                let light_client_span = info_span!(
+                    "light_client_update",
+                    operation = "build_transition",
+                    header_count = tracing::field::Empty
+                );
 
-                info!(message = "Building light client update");
+                info!(
+                    message = "Building light client update",
+                    light_client_root = %btc_light_client_root,
+                    local_root = %btc_local_root
+                );
                 let chain_transition = build_chain_transition_for_light_client_update(
                     btc_rpc.clone(),
                     &bitcoin_mmr,
-                    &light_client_mmr,
+                    &contract_data_engine.checkpointed_block_tree,
                     bitcoin_concurrency_limit,
                 )
+                .instrument(light_client_span.clone())
                 .await?;
+                light_client_span.record("header_count", chain_transition.new_headers.len());
+
+                info!(
+                    message = "Chain transition built",
+                    summary = %chain_transition.summarize()
+                );
 
-                info!("chain transition: {:#?}", chain_transition);
+                // fail fast on a malformed transition rather than after proving
+                chain_transition
+                    .validate_chainwork_monotonicity()
+                    .map_err(|e| eyre::eyre!("Invalid light client update: {}", e))?;
+                if !light_client_update_allowed(
+                    &chain_transition.new_headers,
+                    &config.banned_block_hashes,
+                ) {
+                    rebuild_swaps = Some(confirmed_swaps);
+                    // don't block the bitcoin data engine while the batch is held
+                    drop(bitcoin_mmr);
+                    tokio::time::sleep(BANNED_BLOCK_HOLD_DELAY).await;
+                    continue;
+                }
+
+                // the light client should end up with exactly our MMR, check independently of
+                // the prover before spending time proving
+                let new_leaves = validate_tip_extension(
+                    &chain_transition.parent.mmr_data.leaf,
+                    &chain_transition.parent.header,
+                    &chain_transition.parent_retarget.header,
+                    &chain_transition.new_headers,
+                )
+                .map_err(|e| eyre::eyre!("Invalid light client update: {}", e))?;
+                let expected_root = MmrRoot::from(compute_root_after_append::<Keccak256Hasher>(
+                    &chain_transition.parent_leaf_peaks,
+                    chain_transition.parent.mmr_data.leaf.height + 1,
+                    &new_leaves,
+                ));
+                if expected_root != btc_local_root {
+                    return Err(eyre::eyre!(
+                        "Light client update would produce root {}, but the local root is {}",
+                        expected_root,
+                        btc_local_root
+                    ));
+                }
+                expected_light_client_update = Some((expected_root, new_leaves));
 
-                info!(message = "Light client update built");
+                info!(
+                    message = "Light client update built",
+                    header_count = chain_transition.new_headers.len(),
+                    estimated_proving_time = %format_duration(
+                        proof_generator.estimate_proving_time(&chain_transition)
+                    )
+                );
                 light_client_update = true;
                 rift_program_input_builder =
                     rift_program_input_builder.light_client_input(chain_transition);
@@ -319,8 +479,7 @@ impl SwapWatchtower {
                 });
             }
 
-            // free the locks, we no longer need them
-            drop(light_client_mmr);
+            // free the lock, we no longer need it
             drop(bitcoin_mmr);
 
             rift_program_input_builder = rift_program_input_builder.rift_transaction_input(
@@ -336,11 +495,53 @@ impl SwapWatchtower {
 
             let (public_values_simulated, auxiliary_data) =
                 rift_program_input.get_auxiliary_light_client_data();
+            if let Some((expected_root, new_leaves)) = &expected_light_client_update {
+                let simulated_root = MmrRoot::from(public_values_simulated.newMmrRoot);
+                if simulated_root != *expected_root {
+                    return Err(eyre::eyre!(
+                        "Simulated light client root {} does not match the expected root {}",
+                        simulated_root,
+                        expected_root
+                    ));
+                }
+                check_compressed_leaves(
+                    &auxiliary_data.compressed_leaves,
+                    new_leaves,
+                    public_values_simulated.compressedLeavesCommitment.0,
+                )?;
+
+                // the update reverts unless it starts from the contract's current root, which
+                // the contract data engine can lag behind or run ahead of
+                let onchain_root = MmrRoot::from(rift_exchange.mmrRoot().call().await?._0);
+                if prior_root_rebuilds.check(
+                    onchain_root,
+                    MmrRoot::from(public_values_simulated.previousMmrRoot),
+                )? {
+                    rebuild_swaps = Some(confirmed_swaps);
+                    tokio::time::sleep(PRIOR_ROOT_MISMATCH_REBUILD_DELAY).await;
+                    continue;
+                }
+            }
 
-            let proof = proof_generator
-                .prove(&rift_program_input)
-                .await
-                .map_err(|e| eyre::eyre!("Failed to generate proof: {}", e))?;
+            let Some(proof) = prove_batch(
+                proof_generator.as_ref(),
+                shadow_proof_generator.as_deref(),
+                &rift_program_input,
+                &config,
+                &proving_budget,
+                PROVER_UNAVAILABLE_BACKOFF,
+            )
+            .instrument(info_span!(
+                "generate_proof",
+                swap_count = confirmed_swaps.len(),
+                light_client_update
+            ))
+            .await?
+            else {
+                // the prover was down, the chain may have moved on since the batch was built
+                rebuild_swaps = Some(confirmed_swaps);
+                continue;
+            };
 
             info!("Proof generated: {:?}", proof);
 
@@ -385,15 +586,352 @@ impl SwapWatchtower {
                     (transaction_request, calldata)
                 };
 
+            check_calldata_size(&calldata, config.max_calldata_bytes)?;
+
             let txn = transaction_broadcaster
-                .broadcast_transaction(calldata, transaction_request, PreflightCheck::Simulate)
+                .broadcast_transaction(calldata, transaction_request, preflight.preflight_check())
+                .instrument(info_span!(
+                    "broadcast_swap_proof",
+                    swap_count = confirmed_swaps.len(),
+                    light_client_update
+                ))
                 .await?;
+            preflight.record_outcome(txn.is_success());
+            if let TransactionExecutionResult::Success(receipt) = &txn {
+                let cost_wei = transaction_cost_wei(receipt);
+                info!(
+                    message = "Swap proof transaction cost",
+                    light_client_update,
+                    gas_used = receipt.gas_used,
+                    effective_gas_price = receipt.effective_gas_price,
+                    cost_wei = %cost_wei,
+                    cost_eth = %format_ether(cost_wei)
+                );
+                if light_client_update {
+                    match check_light_client_updated_log(
+                        receipt,
+                        evm_address,
+                        MmrRoot::from(public_values_simulated.newMmrRoot.0),
+                    ) {
+                        Ok(()) => info!("Light client update confirmed by transaction logs"),
+                        Err(e) => warn!(
+                            message = "Light client update not confirmed by transaction logs",
+                            error = %e
+                        ),
+                    }
+                }
+            }
             info!("Submitted swap proof with txn exeuction result: {:?}", txn);
             // TODO: Handle txn failure cases, and retry logic
         }
     }
 }
 
+/// Drives `future` to completion, calling `on_heartbeat` with the elapsed time every `interval`
+async fn with_heartbeat<T>(
+    future: impl Future<Output = T>,
+    interval: Duration,
+    mut on_heartbeat: impl FnMut(Duration),
+) -> T {
+    let start = Instant::now();
+    tokio::pin!(future);
+    let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        tokio::select! {
+            output = &mut future => return output,
+            _ = heartbeat.tick() => on_heartbeat(start.elapsed()),
+        }
+    }
+}
+
+/// Runs `operation`, restarting it each time it takes longer than `timeout`. Errors returned by
+/// `operation` itself are not retried.
+async fn with_startup_timeout<T, Fut>(
+    operation: &str,
+    timeout: Duration,
+    attempts: usize,
+    mut operation_fn: impl FnMut() -> Fut,
+) -> eyre::Result<T>
+where
+    Fut: Future<Output = eyre::Result<T>>,
+{
+    for attempt in 1..=attempts {
+        match tokio::time::timeout(timeout, operation_fn()).await {
+            Ok(result) => return result,
+            Err(_) => warn!(
+                message = "Startup rpc call timed out",
+                operation,
+                attempt,
+                timeout = %format_duration(timeout)
+            ),
+        }
+    }
+    Err(eyre::eyre!(
+        "{} timed out {} times after {}",
+        operation,
+        attempts,
+        format_duration(timeout)
+    ))
+}
+
+/// Awaits the primary proof alongside the shadow proof, if a shadow prover is configured, and
+/// only returns the primary proof when both commit to the same public values. Catches a
+/// compromised or buggy prover before its proof is broadcast.
+async fn prove_with_shadow<T>(
+    primary: impl Future<Output = eyre::Result<T>>,
+    shadow: Option<impl Future<Output = eyre::Result<T>>>,
+    public_values: impl Fn(&T) -> &[u8],
+) -> eyre::Result<T> {
+    let Some(shadow) = shadow else {
+        return primary.await;
+    };
+    let (primary_proof, shadow_proof) = tokio::try_join!(primary, shadow)?;
+    if public_values(&primary_proof) != public_values(&shadow_proof) {
+        return Err(eyre::eyre!(
+            "Shadow prover disagrees with the primary prover on the public values, refusing to broadcast"
+        ));
+    }
+    Ok(primary_proof)
+}
+
+/// Calls `call`, retrying up to `retries` times on error with a delay starting at
+/// `initial_backoff` and doubling each retry. For transient failures reaching the prover, as
+/// opposed to regenerating a proof that failed verification.
+async fn retry_with_backoff<T, E, Fut>(
+    retries: u32,
+    initial_backoff: Duration,
+    mut call: impl FnMut() -> Fut,
+) -> Result<T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let mut backoff = initial_backoff;
+    for retry in 1..=retries {
+        match call().await {
+            Ok(output) => return Ok(output),
+            Err(e) => {
+                warn!(
+                    message = "Prover call failed, retrying",
+                    retry,
+                    retries,
+                    backoff = %format_duration(backoff),
+                    error = %e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+    }
+    call().await
+}
+
+/// The prover calls made by the finalizer, implemented by `RiftProofGenerator`
+trait BatchProver: Send + Sync {
+    fn prove(
+        &self,
+        input: &RiftProgramInput,
+    ) -> impl Future<Output = Result<Proof, Box<dyn std::error::Error + Send + Sync>>> + Send;
+
+    fn verify(&self, proof: &Proof) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Checks the prover can be reached without proving anything
+    fn check_reachable(
+        &self,
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+}
+
+impl BatchProver for RiftProofGenerator {
+    async fn prove(
+        &self,
+        input: &RiftProgramInput,
+    ) -> Result<Proof, Box<dyn std::error::Error + Send + Sync>> {
+        RiftProofGenerator::prove(self, input).await
+    }
+
+    fn verify(&self, proof: &Proof) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        RiftProofGenerator::verify(self, proof)
+    }
+
+    async fn check_reachable(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        RiftProofGenerator::check_reachable(self).await
+    }
+}
+
+/// Proves a batch, checked against the shadow prover if one is configured. A failed proof is
+/// only a prover outage if a prover then fails its reachability check: proving enters the
+/// degraded state, the cheap check is repeated every `probe_interval` until the provers are
+/// reachable again, and `None` is returned so the held batch is rebuilt and proven again. Any
+/// other failure, e.g. the program failing to execute, is deterministic and returned as is.
+async fn prove_batch<P: BatchProver>(
+    proof_generator: &P,
+    shadow_proof_generator: Option<&P>,
+    rift_program_input: &RiftProgramInput,
+    config: &SwapWatchtowerConfig,
+    proving_budget: &Mutex<ProvingBudget>,
+    probe_interval: Duration,
+) -> eyre::Result<Option<Proof>> {
+    let shadow_proof = shadow_proof_generator.map(|shadow_proof_generator| {
+        async move {
+            retry_with_backoff(
+                config.prover_call_retries,
+                PROVER_CALL_INITIAL_BACKOFF,
+                || async move {
+                    start_prover_call(proving_budget).await;
+                    shadow_proof_generator.prove(rift_program_input).await
+                },
+            )
+            .await
+            .map_err(|e| eyre::eyre!("Failed to generate shadow proof: {}", e))
+        }
+        .instrument(info_span!("generate_shadow_proof"))
+    });
+    let primary_proof = generate_verified_proof(
+        PROOF_GENERATION_ATTEMPTS,
+        || async move {
+            retry_with_backoff(
+                config.prover_call_retries,
+                PROVER_CALL_INITIAL_BACKOFF,
+                || async move {
+                    start_prover_call(proving_budget).await;
+                    with_heartbeat(
+                        proof_generator.prove(rift_program_input),
+                        PROOF_HEARTBEAT_INTERVAL,
+                        |elapsed| {
+                            info!(
+                                message = "Proof generation still running",
+                                elapsed = %format_duration(elapsed)
+                            )
+                        },
+                    )
+                    .await
+                },
+            )
+            .await
+            .map_err(|e| eyre::eyre!("Failed to generate proof: {}", e))
+        },
+        |proof| {
+            if config.verify_proofs_locally {
+                proof_generator.verify(proof)
+            } else {
+                Ok(())
+            }
+        },
+    );
+    let error = match prove_with_shadow(primary_proof, shadow_proof, |proof: &Proof| {
+        proof.public_values.as_slice()
+    })
+    .await
+    {
+        Ok(proof) => return Ok(Some(proof)),
+        Err(error) => error,
+    };
+
+    let provers = std::iter::once(proof_generator).chain(shadow_proof_generator);
+    let Some(unreachable) = check_provers_reachable(provers.clone()).await.err() else {
+        return Err(error);
+    };
+    let mut prover_health = ProverHealth::default();
+    prover_health.record_unavailable(&error, unreachable.as_ref(), Instant::now());
+    loop {
+        tokio::time::sleep(probe_interval).await;
+        match check_provers_reachable(provers.clone()).await {
+            Ok(()) => {
+                prover_health.record_available(Instant::now());
+                return Ok(None);
+            }
+            Err(e) => prover_health.record_unavailable(&error, e.as_ref(), Instant::now()),
+        }
+    }
+}
+
+/// Checks each prover in turn, failing with the first unreachable one
+async fn check_provers_reachable<'a, P: BatchProver + 'a>(
+    provers: impl IntoIterator<Item = &'a P>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for prover in provers {
+        prover.check_reachable().await?;
+    }
+    Ok(())
+}
+
+/// Whether the prover is reachable. A prover found unreachable after proving failed enters a
+/// degraded state where proving pauses and only its reachability is checked, until it's
+/// reachable again. Entering and leaving are logged once each, as the health signal for
+/// operators.
+#[derive(Debug, Default)]
+struct ProverHealth {
+    unavailable_since: Option<Instant>,
+}
+
+impl ProverHealth {
+    /// Records a failed reachability check, after proving failed with `error`
+    fn record_unavailable(
+        &mut self,
+        error: &eyre::Report,
+        unreachable: &(dyn std::error::Error + Send + Sync),
+        now: Instant,
+    ) {
+        match self.unavailable_since {
+            None => {
+                self.unavailable_since = Some(now);
+                error!(
+                    message = "Prover unavailable, pausing proving",
+                    prover_health = "degraded",
+                    error = %error,
+                    unreachable = %unreachable
+                );
+            }
+            Some(unavailable_since) => warn!(
+                message = "Prover still unavailable",
+                prover_health = "degraded",
+                unavailable_for = %format_duration(now.duration_since(unavailable_since)),
+                unreachable = %unreachable
+            ),
+        }
+    }
+
+    fn record_available(&mut self, now: Instant) {
+        if let Some(unavailable_since) = self.unavailable_since.take() {
+            info!(
+                message = "Prover reachable again, resuming proving",
+                prover_health = "healthy",
+                unavailable_for = %format_duration(now.duration_since(unavailable_since))
+            );
+        }
+    }
+}
+
+/// Generates a proof, regenerating it if `verify` rejects it, so a bad proof fails locally
+/// instead of reverting onchain.
+async fn generate_verified_proof<T, E, Fut>(
+    attempts: usize,
+    mut generate: impl FnMut() -> Fut,
+    verify: impl Fn(&T) -> Result<(), E>,
+) -> eyre::Result<T>
+where
+    Fut: Future<Output = eyre::Result<T>>,
+    E: std::fmt::Display,
+{
+    for attempt in 1..=attempts {
+        let proof = generate()
+            .instrument(info_span!("proof_attempt", attempt))
+            .await?;
+        match verify(&proof) {
+            Ok(()) => return Ok(proof),
+            Err(e) => warn!(
+                message = "Proof failed local verification",
+                attempt,
+                error = %e
+            ),
+        }
+    }
+    Err(eyre::eyre!(
+        "Proof failed local verification after {} attempts",
+        attempts
+    ))
+}
+
 // Computes how far back in terms of bitcoin blocks to search for swaps based on the oldest active deposit
 async fn compute_block_search_range(
     evm_rpc: Arc<dyn Provider<PubSubFrontend>>,
@@ -642,6 +1180,180 @@ fn get_leaf_and_block_header_from_block_info(
     Ok((leaf, block_header))
 }
 
+/// Light client state needed to build a chain transition.
+struct LightClientSnapshot {
+    bitcoin_tip_height: usize,
+    current_mmr_root: Digest,
+    current_mmr_bagged_peak: Digest,
+    parent_leaf_peaks: Vec<Digest>,
+    current_tip_with_proof: ProvenLeaf,
+    parent_with_proof: ProvenLeaf,
+    parent_retarget_with_proof: ProvenLeaf,
+    disposed_leaves: Vec<BlockLeaf>,
+}
+
+/// Finds the last leaf the light client shares with the bitcoin data engine by comparing their
+/// leaves at the same indices back from the light client's tip with `find_divergence`, doubling
+/// the window compared until it reaches a shared leaf. Returns the shared leaf's index and the
+/// light client leaves after it, in chain order.
+async fn find_common_ancestor(
+    bitcoin_mmr: &IndexedMMR<Keccak256Hasher>,
+    light_client_mmr: &CheckpointedBlockTree<Keccak256Hasher>,
+    tip_leaf_index: usize,
+) -> eyre::Result<(usize, Vec<BlockLeaf>)> {
+    let mut window = 1;
+    loop {
+        let start = (tip_leaf_index + 1).saturating_sub(window);
+        let mut light_client_leaves = Vec::with_capacity(window);
+        let mut bitcoin_leaves = Vec::with_capacity(window);
+        for leaf_index in start..=tip_leaf_index {
+            light_client_leaves.push(
+                light_client_mmr
+                    .get_leaf_by_leaf_index(leaf_index)
+                    .await?
+                    .ok_or_else(|| {
+                        eyre::eyre!("Failed to get light client leaf at index {}", leaf_index)
+                    })?,
+            );
+            // the bitcoin data engine's chain can be shorter than the light client's
+            if let Some(leaf) = bitcoin_mmr.get_leaf_by_leaf_index(leaf_index).await? {
+                if bitcoin_leaves.len() == leaf_index - start {
+                    bitcoin_leaves.push(leaf);
+                }
+            }
+        }
+
+        match find_divergence(&light_client_leaves, &bitcoin_leaves) {
+            None => return Ok((tip_leaf_index, Vec::new())),
+            // every leaf in the window diverges, the common ancestor is further back
+            Some(0) if start > 0 => window *= 2,
+            Some(0) => return Err(eyre::eyre!("Failed to find parent leaf")),
+            Some(divergence) => {
+                return Ok((
+                    start + divergence - 1,
+                    light_client_leaves.split_off(divergence),
+                ))
+            }
+        }
+    }
+}
+
+/// Finds a "parent" leaf that both the light client and bitcoin core know about and agree
+/// about being in the longest chain, and reads everything the transition needs from the
+/// light client mmr. The light client lock is only held for the duration of this call.
+async fn snapshot_light_client_state(
+    bitcoin_mmr: &IndexedMMR<Keccak256Hasher>,
+    light_client_mmr: &RwLock<CheckpointedBlockTree<Keccak256Hasher>>,
+) -> eyre::Result<LightClientSnapshot> {
+    // all lookups happen on local databases: this should be fast
+    let light_client_mmr = light_client_mmr.read().await;
+
+    let current_mmr_root = light_client_mmr.get_root().await?;
+    let current_mmr_bagged_peak = light_client_mmr.get_bagged_peak().await?;
+
+    let current_tip_leaf_index = light_client_mmr.get_leaf_count().await? - 1;
+    let current_tip_leaf = light_client_mmr
+        .get_leaf_by_leaf_index(current_tip_leaf_index)
+        .await?
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "Failed to get current leaf at index {}",
+                current_tip_leaf_index
+            )
+        })?;
+    // leaves to remove from the light client (assumed that the light client is always a subset of the bitcoin data engine)
+    let (parent_leaf_index, disposed_leaves) =
+        find_common_ancestor(bitcoin_mmr, &light_client_mmr, current_tip_leaf_index).await?;
+    let parent_leaf = if disposed_leaves.is_empty() {
+        current_tip_leaf
+    } else {
+        info!(
+            message = "Light client diverges from the bitcoin data engine",
+            disposed_leaves = disposed_leaves.len(),
+            parent_leaf_index
+        );
+        // query light client for parent leaf, should always succeed
+        light_client_mmr
+            .get_leaf_by_leaf_index(parent_leaf_index)
+            .await?
+            .ok_or_else(|| {
+                eyre::eyre!("Failed to get parent leaf at index {}", parent_leaf_index)
+            })?
+    };
+
+    // the parent leaf being in both mmrs isn't enough, a corrupt mmr could contain it on top of
+    // a different history, so check the mmrs agree on every leaf up to it
+    let prefix_leaf_count = parent_leaf_index + 1;
+    let light_client_prefix_peaks = light_client_mmr
+        .get_peaks(Some(leaf_count_to_elements_count(prefix_leaf_count)))
+        .await?;
+    if !bitcoin_mmr
+        .verify_shared_prefix(prefix_leaf_count, &light_client_prefix_peaks)
+        .await?
+    {
+        return Err(eyre::eyre!(
+            "Light client and bitcoin data engine disagree on the chain up to common ancestor at height {}",
+            parent_leaf.height
+        ));
+    }
+
+    // get the peaks of the light client mmr as if the parent leaf was the tip of the MMR
+    let parent_leaf_peaks = light_client_mmr
+        .get_peaks(Some(map_leaf_index_to_element_index(parent_leaf_index) + 1))
+        .await?;
+
+    let parent_retarget_height = get_retarget_height_from_block_height(parent_leaf.height);
+    let parent_retarget_leaf = bitcoin_mmr
+        .get_leaf_by_leaf_index(parent_retarget_height as usize)
+        .await?
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "Failed to get parent retarget leaf at index {}",
+                parent_retarget_height
+            )
+        })?;
+
+    let parent_retarget_inclusion_proof = light_client_mmr
+        .get_circuit_proof(parent_retarget_height as usize, None)
+        .await?;
+
+    let parent_inclusion_proof = light_client_mmr
+        .get_circuit_proof(parent_leaf_index, None)
+        .await?;
+
+    let current_tip_proof = light_client_mmr
+        .get_circuit_proof(current_tip_leaf_index, None)
+        .await?;
+
+    let parent_retarget_with_proof = ProvenLeaf {
+        leaf: parent_retarget_leaf,
+        proof: parent_retarget_inclusion_proof,
+    };
+
+    let parent_with_proof = ProvenLeaf {
+        leaf: parent_leaf,
+        proof: parent_inclusion_proof,
+    };
+
+    let current_tip_with_proof = ProvenLeaf {
+        leaf: current_tip_leaf,
+        proof: current_tip_proof,
+    };
+
+    let bitcoin_tip_height = bitcoin_mmr.get_leaf_count().await? - 1;
+
+    Ok(LightClientSnapshot {
+        bitcoin_tip_height,
+        current_mmr_root,
+        current_mmr_bagged_peak,
+        parent_leaf_peaks,
+        current_tip_with_proof,
+        parent_with_proof,
+        parent_retarget_with_proof,
+        disposed_leaves,
+    })
+}
+
 /// Builds a chain transition for updating the light client state.
 ///
 /// This function creates a ChainTransition that represents the progression from
@@ -650,8 +1362,9 @@ fn get_leaf_and_block_header_from_block_info(
 ///
 /// # Arguments
 ///
-/// * `bitcoin_data_engine` - The Bitcoin data engine that contains the latest chain data
-/// * `contract_data_engine` - The contract data engine that contains the current on-chain state
+/// * `bitcoin_mmr` - The Bitcoin data engine's MMR that contains the latest chain data
+/// * `light_client_mmr` - The contract data engine's block tree that contains the current on-chain state,
+///   only read-locked while the light client state is snapshotted
 ///
 /// # Returns
 ///
@@ -660,14 +1373,15 @@ fn get_leaf_and_block_header_from_block_info(
 pub async fn build_chain_transition_for_light_client_update<'a>(
     btc_rpc: Arc<AsyncBitcoinClient>,
     bitcoin_mmr: &RwLockReadGuard<'a, IndexedMMR<Keccak256Hasher>>,
-    light_client_mmr: &RwLockReadGuard<'a, CheckpointedBlockTree<Keccak256Hasher>>,
+    light_client_mmr: &RwLock<CheckpointedBlockTree<Keccak256Hasher>>,
     bitcoin_concurrency_limit: usize,
 ) -> eyre::Result<ChainTransition> {
     info!("Building chain transition");
-    // Find a "parent" leaf that both the light client and bitcoin core know about and agree
-    // about being in the longest chain
-    // Also get the current tip, the best leaf the light client knows about
-    let (
+    // Everything after the snapshot is RPC bound, so release the light client lock first.
+    // If the contract data engine advances past the snapshot, current_mmr_root no longer
+    // matches the onchain root and the update is rejected at simulation, to be rebuilt on
+    // the next batch.
+    let LightClientSnapshot {
         bitcoin_tip_height,
         current_mmr_root,
         current_mmr_bagged_peak,
@@ -676,110 +1390,7 @@ pub async fn build_chain_transition_for_light_client_update<'a>(
         parent_with_proof,
         parent_retarget_with_proof,
         disposed_leaves,
-    ) = {
-        // lock both the light client and bitcoin core mmrs while we search
-        // b/c all lookups happen on local databases: this should be fast
-
-        let current_mmr_root = light_client_mmr.get_root().await?;
-        let current_mmr_bagged_peak = light_client_mmr.get_bagged_peak().await?;
-
-        let current_tip_leaf_index = light_client_mmr.get_leaf_count().await? - 1;
-        let current_tip_leaf = light_client_mmr
-            .get_leaf_by_leaf_index(current_tip_leaf_index)
-            .await?
-            .ok_or_else(|| {
-                eyre::eyre!(
-                    "Failed to get current leaf at index {}",
-                    current_tip_leaf_index
-                )
-            })?;
-        // iterate to find the parent leaf
-        let mut parent_leaf_index = current_tip_leaf_index;
-        let mut parent_leaf = current_tip_leaf;
-        // leaves to remove from the light client (assumed that the light client is always a subset of the bitcoin data engine)
-        let mut disposed_leaves = Vec::new();
-        loop {
-            // query bitcoin data engine for parent leaf
-            let parent_leaf_hash = parent_leaf.hash::<Keccak256Hasher>();
-            let potential_parent = bitcoin_mmr.get_leaf_by_leaf_hash(&parent_leaf_hash).await?;
-            if potential_parent.is_some() {
-                break;
-            }
-            // if we're here, the parent leaf is not in the bitcoin data engine, so we need to remove it from the light client
-            disposed_leaves.push(parent_leaf);
-            parent_leaf_index -= 1;
-            if parent_leaf_index == 0 {
-                return Err(eyre::eyre!("Failed to find parent leaf"));
-            }
-            // query light client for parent leaf, should always succeed
-            parent_leaf = light_client_mmr
-                .get_leaf_by_leaf_index(parent_leaf_index)
-                .await?
-                .ok_or_else(|| {
-                    eyre::eyre!("Failed to get parent leaf at index {}", parent_leaf_index)
-                })?;
-            info!(
-                "Could not find parent leaf {} in bitcoin data engine, checking next parent...",
-                hex::encode(parent_leaf_hash)
-            );
-        }
-
-        // get the peaks of the light client mmr as if the parent leaf was the tip of the MMR
-        let parent_leaf_peaks = light_client_mmr
-            .get_peaks(Some(map_leaf_index_to_element_index(parent_leaf_index) + 1))
-            .await?;
-
-        let parent_retarget_height = get_retarget_height_from_block_height(parent_leaf.height);
-        let parent_retarget_leaf = bitcoin_mmr
-            .get_leaf_by_leaf_index(parent_retarget_height as usize)
-            .await?
-            .ok_or_else(|| {
-                eyre::eyre!(
-                    "Failed to get parent retarget leaf at index {}",
-                    parent_retarget_height
-                )
-            })?;
-
-        let parent_retarget_inclusion_proof = light_client_mmr
-            .get_circuit_proof(parent_retarget_height as usize, None)
-            .await?;
-
-        let parent_inclusion_proof = light_client_mmr
-            .get_circuit_proof(parent_leaf_index, None)
-            .await?;
-
-        let current_tip_proof = light_client_mmr
-            .get_circuit_proof(current_tip_leaf_index, None)
-            .await?;
-
-        let parent_retarget_with_proof = ProvenLeaf {
-            leaf: parent_retarget_leaf,
-            proof: parent_retarget_inclusion_proof,
-        };
-
-        let parent_with_proof = ProvenLeaf {
-            leaf: parent_leaf,
-            proof: parent_inclusion_proof,
-        };
-
-        let current_tip_with_proof = ProvenLeaf {
-            leaf: current_tip_leaf,
-            proof: current_tip_proof,
-        };
-
-        let bitcoin_tip_height = bitcoin_mmr.get_leaf_count().await? - 1;
-
-        (
-            bitcoin_tip_height,
-            current_mmr_root,
-            current_mmr_bagged_peak,
-            parent_leaf_peaks,
-            current_tip_with_proof,
-            parent_with_proof,
-            parent_retarget_with_proof,
-            disposed_leaves,
-        )
-    };
+    } = snapshot_light_client_state(bitcoin_mmr, light_client_mmr).await?;
 
     info!(message = "Building parent header");
     let parent_header: Header = bitcoincore_rpc_async::bitcoin::consensus::encode::serialize(
@@ -803,6 +1414,16 @@ pub async fn build_chain_transition_for_light_client_update<'a>(
         .try_into()
         .map_err(|e| eyre::eyre!("Failed to serialize parent retarget header: {}", e))?;
 
+    if !parent_with_proof.leaf.verify_consistency(&parent_header)
+        || !parent_retarget_with_proof
+            .leaf
+            .verify_consistency(&parent_retarget_header)
+    {
+        return Err(eyre::eyre!(
+            "Light client leaves do not match the headers returned by bitcoin rpc"
+        ));
+    }
+
     // finally get the new headers from bitcoin data engine
     let new_headers = if bitcoin_tip_height != (parent_with_proof.leaf.height as usize + 1) {
         btc_rpc
@@ -846,3 +1467,902 @@ pub async fn build_chain_transition_for_light_client_update<'a>(
         new_headers,
     })
 }
+
+/// Whether the bitcoin data engine's chain no longer contains the light client's tip, so an
+/// update would have to reorg the light client rather than only extend it
+async fn light_client_tip_reorged(
+    bitcoin_mmr: &IndexedMMR<Keccak256Hasher>,
+    light_client_mmr: &CheckpointedBlockTree<Keccak256Hasher>,
+) -> eyre::Result<bool> {
+    let tip_leaf_index = light_client_mmr.get_leaf_count().await? - 1;
+    let (_, disposed_leaves) =
+        find_common_ancestor(bitcoin_mmr, light_client_mmr, tip_leaf_index).await?;
+    Ok(!disposed_leaves.is_empty())
+}
+
+/// If `reorged` reports the local chain reorging the light client's tip, waits `settle_delay`
+/// and checks again, returning whether the reorg survived the wait
+async fn settle_light_client_reorg<F, Fut>(
+    settle_delay: Duration,
+    mut reorged: F,
+) -> eyre::Result<bool>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = eyre::Result<bool>>,
+{
+    if !reorged().await? {
+        return Ok(false);
+    }
+    info!(
+        message = "Local bitcoin chain reorged the light client tip, settling",
+        settle_delay = %format_duration(settle_delay)
+    );
+    tokio::time::sleep(settle_delay).await;
+    let reorged = reorged().await?;
+    if reorged {
+        info!("Light client tip reorg persisted after settling");
+    } else {
+        info!("Local bitcoin chain reorged back onto the light client tip while settling");
+    }
+    Ok(reorged)
+}
+
+/// Counts consecutive rebuilds of light client updates whose prior root didn't match the
+/// contract, so a contract data engine that never catches up is escalated instead of retried
+/// forever
+struct PriorRootRebuilds {
+    max_rebuilds: u32,
+    rebuilds: u32,
+}
+
+impl PriorRootRebuilds {
+    fn new(max_rebuilds: u32) -> Self {
+        Self {
+            max_rebuilds,
+            rebuilds: 0,
+        }
+    }
+
+    /// Whether an update built on `prior_root` has to be rebuilt to apply on top of
+    /// `onchain_root`. Errors once the update has been rebuilt `max_rebuilds` times in a row
+    /// without converging.
+    fn check(&mut self, onchain_root: MmrRoot, prior_root: MmrRoot) -> eyre::Result<bool> {
+        let Err(e) = check_prior_root(onchain_root, prior_root) else {
+            self.rebuilds = 0;
+            return Ok(false);
+        };
+        if self.rebuilds >= self.max_rebuilds {
+            error!(
+                message = "Light client update prior root never matched the contract",
+                rebuilds = self.rebuilds,
+                error = %e
+            );
+            return Err(e.wrap_err(format!(
+                "Gave up rebuilding the light client update after {} attempts",
+                self.rebuilds
+            )));
+        }
+        self.rebuilds += 1;
+        warn!(
+            message = "Rebuilding light client update",
+            rebuild = self.rebuilds,
+            max_rebuilds = self.max_rebuilds,
+            error = %e
+        );
+        Ok(true)
+    }
+}
+
+/// Errors if a light client update built on `prior_root` would not apply on top of
+/// `onchain_root`
+fn check_prior_root(onchain_root: MmrRoot, prior_root: MmrRoot) -> eyre::Result<()> {
+    if prior_root != onchain_root {
+        return Err(eyre::eyre!(
+            "Light client update starts from root {}, but the on-chain root is {}",
+            prior_root,
+            onchain_root
+        ));
+    }
+    Ok(())
+}
+
+/// Errors unless `receipt` has a `BitcoinLightClientUpdated` event from `rift_exchange_address`
+/// advancing the light client to `expected_new_root`. Confirms the update from the transaction
+/// itself rather than waiting for the contract data engine to sync it.
+fn check_light_client_updated_log(
+    receipt: &TransactionReceipt,
+    rift_exchange_address: Address,
+    expected_new_root: MmrRoot,
+) -> eyre::Result<()> {
+    let event = receipt
+        .inner
+        .logs()
+        .iter()
+        .filter(|log| log.address() == rift_exchange_address)
+        .find_map(|log| RiftExchange::BitcoinLightClientUpdated::decode_log(&log.inner, false).ok())
+        .ok_or_else(|| eyre::eyre!("Transaction did not emit a BitcoinLightClientUpdated event"))?;
+    let new_root = MmrRoot::from(event.data.newMmrRoot.0);
+    if new_root != expected_new_root {
+        return Err(eyre::eyre!(
+            "Light client updated to root {}, expected {}",
+            new_root,
+            expected_new_root
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a light client update over `headers` may be proven. An update committing a banned
+/// block is refused with an alert rather than an error, the batch is held until the local chain
+/// moves off the block instead of stopping the watchtower
+fn light_client_update_allowed(
+    headers: &[Header],
+    banned_block_hashes: &HashSet<[u8; 32]>,
+) -> bool {
+    match check_banned_blocks(headers, banned_block_hashes) {
+        Ok(()) => true,
+        Err(e) => {
+            error!(
+                message = "Refusing light client update, holding the batch",
+                error = %e
+            );
+            false
+        }
+    }
+}
+
+/// Takes the batch queued for a rebuild if there is one, otherwise waits for the next batch
+async fn next_confirmed_swaps<T>(
+    rebuild: &mut Option<T>,
+    confirmed_swaps_rx: &mut UnboundedReceiver<T>,
+) -> eyre::Result<T> {
+    if let Some(batch) = rebuild.take() {
+        return Ok(batch);
+    }
+    confirmed_swaps_rx
+        .recv()
+        .await
+        .ok_or_else(|| eyre::eyre!("Confirmed swaps channel receiver unexpectedly closed"))
+}
+
+/// The contract only commits to the hash of `compressedBlockLeaves`, so a compression bug
+/// surfaces on-chain as an opaque proof verification failure. Check the calldata encodes
+/// exactly the leaves we appended, and hashes to the commitment we're about to prove.
+fn check_compressed_leaves(
+    compressed_leaves: &[u8],
+    expected_leaves: &[BlockLeaf],
+    commitment: Digest,
+) -> eyre::Result<()> {
+    if compressed_leaves != expected_leaves.compress().as_slice() {
+        return Err(eyre::eyre!(
+            "Compressed block leaves do not encode the {} appended leaves, leaf compression is broken",
+            expected_leaves.len()
+        ));
+    }
+    if Keccak256Hasher::hash(compressed_leaves) != commitment {
+        return Err(eyre::eyre!(
+            "Compressed block leaves do not hash to the leaves commitment, leaf compression is broken"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rift_core::giga::RustProofType;
+    use rift_sdk::{proof_generator::ProofGeneratorType, DatabaseLocation};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_reorg_resolved_during_settle_delay() {
+        // the local tip reorgs back onto the light client's chain while settling
+        let mut checks = vec![true, false].into_iter();
+        let reorged = settle_light_client_reorg(Duration::from_millis(10), || {
+            let reorged = checks.next().unwrap();
+            async move { Ok(reorged) }
+        })
+        .await
+        .unwrap();
+        assert!(!reorged);
+        assert!(checks.next().is_none());
+
+        let mut checks = vec![true, true].into_iter();
+        let reorged = settle_light_client_reorg(Duration::from_millis(10), || {
+            let reorged = checks.next().unwrap();
+            async move { Ok(reorged) }
+        })
+        .await
+        .unwrap();
+        assert!(reorged);
+
+        // a light client tip the local chain still contains doesn't wait at all
+        let mut checks = vec![false].into_iter();
+        let reorged = settle_light_client_reorg(Duration::from_secs(3600), || {
+            let reorged = checks.next().unwrap();
+            async move { Ok(reorged) }
+        })
+        .await
+        .unwrap();
+        assert!(!reorged);
+        assert!(checks.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_light_client_tip_reorged() -> eyre::Result<()> {
+        let leaf = |height: u32, fork: u8| BlockLeaf {
+            block_hash: [height as u8 + 1 + fork; 32],
+            cumulative_chainwork: [height as u8 + 1; 32],
+            height,
+        };
+        let light_client_leaves: Vec<BlockLeaf> = (0..8).map(|height| leaf(height, 0)).collect();
+        let mut light_client_mmr =
+            CheckpointedBlockTree::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        light_client_mmr
+            .create_seed_checkpoint(&light_client_leaves)
+            .await?;
+
+        // new blocks on top of the light client's tip, the roots differ but nothing reorged
+        let extended_leaves: Vec<BlockLeaf> = (0..10).map(|height| leaf(height, 0)).collect();
+        let mut extended_mmr =
+            IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        extended_mmr.batch_append(&extended_leaves).await?;
+        assert_ne!(
+            extended_mmr.get_root().await?,
+            light_client_mmr.get_root().await?
+        );
+        assert!(!light_client_tip_reorged(&extended_mmr, &light_client_mmr).await?);
+
+        let reorged_leaves: Vec<BlockLeaf> = (0..10)
+            .map(|height| leaf(height, if height >= 7 { 100 } else { 0 }))
+            .collect();
+        let mut reorged_mmr =
+            IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        reorged_mmr.batch_append(&reorged_leaves).await?;
+        assert!(light_client_tip_reorged(&reorged_mmr, &light_client_mmr).await?);
+        Ok(())
+    }
+
+    fn receipt_with_logs(logs: serde_json::Value) -> TransactionReceipt {
+        serde_json::from_value(serde_json::json!({
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "transactionIndex": "0x0",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "blockNumber": "0x1",
+            "from": "0x0000000000000000000000000000000000000003",
+            "to": "0x0000000000000000000000000000000000000004",
+            "cumulativeGasUsed": "0x30d40",
+            "gasUsed": "0x30d40",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": logs,
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "type": "0x2",
+            "status": "0x1"
+        }))
+        .unwrap()
+    }
+
+    fn light_client_updated_log(address: Address, new_root: [u8; 32]) -> serde_json::Value {
+        let event = RiftExchange::BitcoinLightClientUpdated {
+            priorMmrRoot: [1; 32].into(),
+            newMmrRoot: new_root.into(),
+            compressedBlockLeaves: vec![0u8; 64].into(),
+        };
+        serde_json::json!({
+            "address": address,
+            "topics": [RiftExchange::BitcoinLightClientUpdated::SIGNATURE_HASH],
+            "data": format!("0x{}", hex::encode(event.encode_data())),
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "blockNumber": "0x1",
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "transactionIndex": "0x0",
+            "logIndex": "0x0",
+            "removed": false
+        })
+    }
+
+    #[test]
+    fn test_check_light_client_updated_log() {
+        let rift_exchange_address = Address::repeat_byte(4);
+        let expected_root = MmrRoot::from([2u8; 32]);
+
+        let receipt = receipt_with_logs(serde_json::json!([light_client_updated_log(
+            rift_exchange_address,
+            [2; 32]
+        )]));
+        assert!(
+            check_light_client_updated_log(&receipt, rift_exchange_address, expected_root).is_ok()
+        );
+
+        // the event updated the light client to a different root
+        let receipt = receipt_with_logs(serde_json::json!([light_client_updated_log(
+            rift_exchange_address,
+            [3; 32]
+        )]));
+        assert!(
+            check_light_client_updated_log(&receipt, rift_exchange_address, expected_root)
+                .unwrap_err()
+                .to_string()
+                .contains("expected")
+        );
+
+        // no event, or only one emitted by another contract
+        let receipt = receipt_with_logs(serde_json::json!([]));
+        assert!(
+            check_light_client_updated_log(&receipt, rift_exchange_address, expected_root).is_err()
+        );
+        let receipt = receipt_with_logs(serde_json::json!([light_client_updated_log(
+            Address::repeat_byte(5),
+            [2; 32]
+        )]));
+        assert!(
+            check_light_client_updated_log(&receipt, rift_exchange_address, expected_root).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_prior_root_rebuilds_batch() {
+        let onchain_root = MmrRoot::from([1u8; 32]);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tx.send(vec![1]).unwrap();
+        // the contract data engine lags behind the contract for two builds, then catches up
+        let mut prior_roots = [[0u8; 32], [0u8; 32], [1u8; 32]]
+            .into_iter()
+            .map(MmrRoot::from);
+        let mut rebuilds = PriorRootRebuilds::new(2);
+        let mut rebuild = None;
+        let mut builds = 0;
+        let proven = loop {
+            let batch = next_confirmed_swaps(&mut rebuild, &mut rx).await.unwrap();
+            builds += 1;
+            if rebuilds
+                .check(onchain_root, prior_roots.next().unwrap())
+                .unwrap()
+            {
+                rebuild = Some(batch);
+                // swaps confirmed meanwhile wait behind the rebuild
+                tx.send(vec![builds + 1]).unwrap();
+                continue;
+            }
+            break batch;
+        };
+        assert_eq!(proven, vec![1]);
+        assert_eq!(builds, 3);
+        assert_eq!(rebuilds.rebuilds, 0);
+        assert_eq!(
+            next_confirmed_swaps(&mut rebuild, &mut rx).await.unwrap(),
+            vec![2]
+        );
+
+        // an engine that never catches up is escalated once the rebuilds are exhausted
+        let stale_root = MmrRoot::from([0u8; 32]);
+        assert!(rebuilds.check(onchain_root, stale_root).unwrap());
+        assert!(rebuilds.check(onchain_root, stale_root).unwrap());
+        assert!(rebuilds.check(onchain_root, stale_root).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_banned_block_holds_batch() {
+        let headers: Vec<Header> = (0..3).map(|i| Header([i; 80])).collect();
+        let banned_hash = bitcoin_core_rs::get_block_hash(headers[1].as_bytes()).unwrap();
+
+        assert!(light_client_update_allowed(&headers, &HashSet::new()));
+        assert!(light_client_update_allowed(
+            &headers[..1],
+            &HashSet::from([banned_hash])
+        ));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        // the update is refused without an error and the batch is held for the next round
+        assert!(!light_client_update_allowed(
+            &headers,
+            &HashSet::from([banned_hash])
+        ));
+        let mut rebuild = Some(vec![1]);
+        tx.send(vec![2]).unwrap();
+        assert_eq!(
+            next_confirmed_swaps(&mut rebuild, &mut rx).await.unwrap(),
+            vec![1]
+        );
+        assert_eq!(
+            next_confirmed_swaps(&mut rebuild, &mut rx).await.unwrap(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_check_compressed_leaves() {
+        let leaves: Vec<BlockLeaf> = (0..3u32)
+            .map(|height| BlockLeaf::new([height as u8 + 1; 32], height, [height as u8; 32]))
+            .collect();
+        let compressed = leaves.compress();
+        let commitment = Keccak256Hasher::hash(&compressed);
+
+        assert!(check_compressed_leaves(&compressed, &leaves, commitment).is_ok());
+        // dropped leaf
+        assert!(check_compressed_leaves(&compressed, &leaves[..2], commitment).is_err());
+        // corrupted encoding
+        let mut corrupted = compressed.clone();
+        corrupted[0] ^= 1;
+        assert!(check_compressed_leaves(&corrupted, &leaves, commitment).is_err());
+        // wrong commitment
+        assert!(check_compressed_leaves(&compressed, &leaves, [0; 32]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_light_client_mmr_not_locked_during_transition_build() -> eyre::Result<()> {
+        let leaves: Vec<BlockLeaf> = (0..5u32)
+            .map(|height| BlockLeaf {
+                block_hash: [height as u8 + 1; 32],
+                cumulative_chainwork: [height as u8 + 1; 32],
+                height,
+            })
+            .collect();
+
+        let mut bitcoin_mmr =
+            IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        bitcoin_mmr.batch_append(&leaves).await?;
+
+        let mut light_client_mmr =
+            CheckpointedBlockTree::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        light_client_mmr.create_seed_checkpoint(&leaves).await?;
+        let light_client_mmr = Arc::new(RwLock::new(light_client_mmr));
+
+        let (snapshot_taken_tx, snapshot_taken_rx) = tokio::sync::oneshot::channel();
+        let build_light_client_mmr = light_client_mmr.clone();
+        let build = tokio::spawn(async move {
+            let snapshot =
+                snapshot_light_client_state(&bitcoin_mmr, &build_light_client_mmr).await?;
+            snapshot_taken_tx.send(()).unwrap();
+            // simulate the long, RPC bound remainder of the transition build
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            Ok::<_, eyre::Report>(snapshot)
+        });
+
+        snapshot_taken_rx.await?;
+
+        // the contract data engine can still take the lock mid-build
+        let leaf_count = tokio::time::timeout(Duration::from_millis(500), async {
+            let light_client_mmr = light_client_mmr.write().await;
+            light_client_mmr.get_leaf_count().await
+        })
+        .await
+        .expect("light client mmr lock held during transition build")?;
+        assert_eq!(leaf_count, leaves.len());
+        assert!(!build.is_finished());
+
+        let snapshot = build.await??;
+        assert_eq!(snapshot.current_tip_with_proof.leaf, leaves[4]);
+        assert_eq!(snapshot.parent_with_proof.leaf, leaves[4]);
+        assert!(snapshot.disposed_leaves.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_finds_common_ancestor_of_reorged_light_client() -> eyre::Result<()> {
+        let leaf = |height: u32, fork: u8| BlockLeaf {
+            block_hash: [height as u8 + 1 + fork; 32],
+            cumulative_chainwork: [height as u8 + 1; 32],
+            height,
+        };
+        let light_client_leaves: Vec<BlockLeaf> = (0..8).map(|height| leaf(height, 0)).collect();
+        // the bitcoin data engine reorged the light client's last 3 blocks onto a longer chain
+        let bitcoin_leaves: Vec<BlockLeaf> = (0..10)
+            .map(|height| leaf(height, if height >= 5 { 100 } else { 0 }))
+            .collect();
+
+        let mut bitcoin_mmr =
+            IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        bitcoin_mmr.batch_append(&bitcoin_leaves).await?;
+        let mut light_client_mmr =
+            CheckpointedBlockTree::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        light_client_mmr
+            .create_seed_checkpoint(&light_client_leaves)
+            .await?;
+
+        assert_eq!(
+            find_common_ancestor(&bitcoin_mmr, &light_client_mmr, 7).await?,
+            (4, light_client_leaves[5..].to_vec())
+        );
+        // a light client tip the bitcoin data engine shares has no divergence
+        assert_eq!(
+            find_common_ancestor(&bitcoin_mmr, &light_client_mmr, 4).await?,
+            (4, vec![])
+        );
+
+        let snapshot =
+            snapshot_light_client_state(&bitcoin_mmr, &RwLock::new(light_client_mmr)).await?;
+        assert_eq!(snapshot.parent_with_proof.leaf, light_client_leaves[4]);
+        assert_eq!(snapshot.current_tip_with_proof.leaf, light_client_leaves[7]);
+        // in chain order, as the circuit appends them when checking the prior root
+        assert_eq!(snapshot.disposed_leaves, light_client_leaves[5..].to_vec());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_with_heartbeat_reports_elapsed_time_until_completion() {
+        let mut heartbeats = Vec::new();
+        let output = with_heartbeat(
+            async {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                "proof"
+            },
+            Duration::from_millis(50),
+            |elapsed| heartbeats.push(elapsed),
+        )
+        .await;
+
+        assert_eq!(output, "proof");
+        assert!(heartbeats.len() >= 3, "heartbeats: {:?}", heartbeats);
+        assert!(heartbeats.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(heartbeats[0] >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_generate_verified_proof_regenerates_rejected_proof() {
+        let generated = std::sync::atomic::AtomicUsize::new(0);
+        let proof = generate_verified_proof(
+            2,
+            || async {
+                Ok::<_, eyre::Report>(generated.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
+            },
+            |proof: &usize| {
+                if *proof == 0 {
+                    Err("invalid proof")
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(proof, 1);
+        assert_eq!(generated.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_verified_proof_gives_up_after_attempts() {
+        let result = generate_verified_proof(
+            3,
+            || async { Ok::<_, eyre::Report>(()) },
+            |_: &()| Err("invalid proof"),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_prover_gates_broadcast_on_public_values() {
+        // mock proofs are (prover, public values)
+        fn public_values<'a>(proof: &'a (&str, Vec<u8>)) -> &'a [u8] {
+            &proof.1
+        }
+        let prove = |prover, public_values: &[u8]| {
+            std::future::ready(Ok::<_, eyre::Report>((prover, public_values.to_vec())))
+        };
+
+        // agreeing provers broadcast the primary proof
+        let proof = prove_with_shadow(
+            prove("primary", &[1, 2, 3]),
+            Some(prove("shadow", &[1, 2, 3])),
+            public_values,
+        )
+        .await
+        .unwrap();
+        assert_eq!(proof, ("primary", vec![1, 2, 3]));
+
+        // disagreeing provers block the broadcast
+        let result = prove_with_shadow(
+            prove("primary", &[1, 2, 3]),
+            Some(prove("shadow", &[1, 2, 4])),
+            public_values,
+        )
+        .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Shadow prover disagrees"));
+
+        // a failing shadow prover blocks the broadcast too
+        let result = prove_with_shadow(
+            prove("primary", &[1, 2, 3]),
+            Some(std::future::ready(Err(eyre::eyre!("shadow prover down")))),
+            public_values,
+        )
+        .await;
+        assert!(result.is_err());
+
+        // without a shadow prover the primary proof is used as is
+        let proof = prove_with_shadow(
+            prove("primary", &[1, 2, 3]),
+            None::<std::future::Ready<eyre::Result<(&str, Vec<u8>)>>>,
+            public_values,
+        )
+        .await
+        .unwrap();
+        assert_eq!(proof, ("primary", vec![1, 2, 3]));
+    }
+
+    // Records (span name, parent span name) for every span created while installed
+    struct SpanRecorder(Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let parent = ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name().to_string());
+            self.0
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), parent));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_recovers_from_transient_failure() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let result = retry_with_backoff(2, Duration::from_millis(1), || {
+            let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    Err("prover unreachable")
+                } else {
+                    Ok("proof")
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok("proof"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let result: Result<(), _> = retry_with_backoff(2, Duration::from_millis(1), || {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async { Err("prover unreachable") }
+        })
+        .await;
+        assert_eq!(result, Err("prover unreachable"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_prover_retries_and_shadow_calls_count_against_budget() {
+        let proving_budget = Mutex::new(ProvingBudget::new(Some(3), PROVING_BUDGET_WINDOW));
+        let proving_budget = &proving_budget;
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let calls = &calls;
+        let primary = retry_with_backoff(2, Duration::from_millis(1), || async move {
+            start_prover_call(proving_budget).await;
+            if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                Err("prover unreachable")
+            } else {
+                Ok("proof")
+            }
+        });
+        let shadow = retry_with_backoff(2, Duration::from_millis(1), || async move {
+            start_prover_call(proving_budget).await;
+            Ok::<_, &str>("shadow proof")
+        });
+        assert_eq!(
+            tokio::join!(primary, shadow),
+            (Ok("proof"), Ok("shadow proof"))
+        );
+
+        // the failed call, its retry and the shadow call used up the budget
+        assert!(proving_budget
+            .lock()
+            .unwrap()
+            .try_record_proof(Instant::now())
+            .is_some());
+    }
+
+    // Fails to prove until `proving` is set, and fails its reachability check the next
+    // `unreachable_checks` times
+    #[derive(Default)]
+    struct TestProver {
+        proving: AtomicBool,
+        unreachable_checks: AtomicUsize,
+        prove_calls: AtomicUsize,
+        reachability_checks: AtomicUsize,
+    }
+
+    impl BatchProver for TestProver {
+        async fn prove(
+            &self,
+            _input: &RiftProgramInput,
+        ) -> Result<Proof, Box<dyn std::error::Error + Send + Sync>> {
+            self.prove_calls.fetch_add(1, Ordering::SeqCst);
+            if !self.proving.load(Ordering::SeqCst) {
+                return Err("connection refused".into());
+            }
+            Ok(Proof {
+                proof_type: ProofGeneratorType::Execute,
+                public_values: vec![1],
+                proof: None,
+                cycles: None,
+                duration: Duration::ZERO,
+            })
+        }
+
+        fn verify(&self, _proof: &Proof) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn check_reachable(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.reachability_checks.fetch_add(1, Ordering::SeqCst);
+            match self
+                .unreachable_checks
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            {
+                Ok(_) => Err("connection refused".into()),
+                Err(_) => Ok(()),
+            }
+        }
+    }
+
+    fn test_config() -> SwapWatchtowerConfig {
+        SwapWatchtowerConfig {
+            skip_simulation_after_successes: None,
+            verify_proofs_locally: true,
+            prover_call_retries: 0,
+            max_calldata_bytes: usize::MAX,
+            banned_block_hashes: HashSet::new(),
+            maintenance_windows: vec![],
+            light_client_settle_delay: Duration::ZERO,
+            max_proofs_per_hour: None,
+        }
+    }
+
+    fn test_program_input() -> RiftProgramInput {
+        RiftProgramInput {
+            proof_type: RustProofType::SwapOnly,
+            light_client_input: None,
+            rift_transaction_input: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_prover_holds_batch_until_reachable() {
+        let config = test_config();
+        let proving_budget = Mutex::new(ProvingBudget::new(None, PROVING_BUDGET_WINDOW));
+        let input = test_program_input();
+        let prover = TestProver {
+            unreachable_checks: AtomicUsize::new(3),
+            ..Default::default()
+        };
+
+        // while the prover is down only its reachability is checked, the batch isn't proven
+        // again until it's back
+        let proof = prove_batch(
+            &prover,
+            None,
+            &input,
+            &config,
+            &proving_budget,
+            Duration::from_millis(1),
+        )
+        .await
+        .unwrap();
+        assert!(proof.is_none());
+        assert_eq!(prover.prove_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(prover.reachability_checks.load(Ordering::SeqCst), 4);
+
+        // the held batch is proven on the next round
+        prover.proving.store(true, Ordering::SeqCst);
+        let proof = prove_batch(
+            &prover,
+            None,
+            &input,
+            &config,
+            &proving_budget,
+            Duration::from_millis(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(proof.unwrap().public_values, vec![1]);
+        assert_eq!(prover.prove_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_proving_failure_of_reachable_prover_is_fatal() {
+        let config = test_config();
+        let proving_budget = Mutex::new(ProvingBudget::new(None, PROVING_BUDGET_WINDOW));
+        let input = test_program_input();
+        let prover = TestProver::default();
+
+        // e.g. the program failing to execute, holding the batch would retry it forever
+        let error = prove_batch(
+            &prover,
+            None,
+            &input,
+            &config,
+            &proving_budget,
+            Duration::from_millis(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("Failed to generate proof"));
+        assert_eq!(prover.reachability_checks.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_startup_timeout_retries_hung_call() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let result = with_startup_timeout("test", Duration::from_millis(20), 3, || {
+            let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    std::future::pending::<()>().await;
+                }
+                Ok(call)
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(result, 1);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let err = with_startup_timeout("test", Duration::from_millis(20), 3, || {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            std::future::pending::<eyre::Result<()>>()
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("timed out 3 times"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_generate_verified_proof_spans_each_attempt() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let spans = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanRecorder(spans.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let verified = std::sync::atomic::AtomicBool::new(false);
+        generate_verified_proof(
+            2,
+            || async { Ok::<_, eyre::Report>(()) },
+            |_: &()| {
+                if verified.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err("invalid proof")
+                }
+            },
+        )
+        .instrument(info_span!("generate_proof"))
+        .await
+        .unwrap();
+
+        let spans = spans.lock().unwrap();
+        assert_eq!(
+            *spans,
+            vec![
+                ("generate_proof".to_string(), None),
+                (
+                    "proof_attempt".to_string(),
+                    Some("generate_proof".to_string())
+                ),
+                (
+                    "proof_attempt".to_string(),
+                    Some("generate_proof".to_string())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bitcoin_data_engine::BitcoinDataEngine;
+use bitcoin_light_client_core::leaves::BlockLeaf;
+use serde::Deserialize;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+use tracing::{error, info, warn};
+
+/// A source of bitcoin block hashes trusted independently of the local bitcoin node, used to
+/// catch a compromised or buggy node feeding the bitcoin data engine a different chain
+pub trait TrustedTipOracle: Send + Sync {
+    /// Block hash (display byte order, same as `BlockLeaf::block_hash`) of the block at `height`
+    /// on the oracle's best chain, or `None` if the oracle hasn't seen that height yet
+    fn block_hash_at_height(
+        &self,
+        height: u32,
+    ) -> impl Future<Output = eyre::Result<Option<[u8; 32]>>> + Send;
+}
+
+/// Queries an Esplora compatible block explorer API (e.g. https://blockstream.info/api)
+pub struct EsploraTipOracle {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl EsploraTipOracle {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl TrustedTipOracle for EsploraTipOracle {
+    async fn block_hash_at_height(&self, height: u32) -> eyre::Result<Option<[u8; 32]>> {
+        let response = self
+            .client
+            .get(format!("{}/block-height/{}", self.base_url, height))
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let block_hash = hex::decode(response.error_for_status()?.text().await?.trim())?
+            .try_into()
+            .map_err(|_| eyre::eyre!("Oracle returned a block hash that is not 32 bytes"))?;
+        Ok(Some(block_hash))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TipComparison {
+    Matches,
+    /// The oracle has no block at the local tip's height yet
+    Unavailable,
+    Diverged {
+        height: u32,
+        local_block_hash: [u8; 32],
+        trusted_block_hash: [u8; 32],
+    },
+}
+
+impl TipComparison {
+    /// Short guidance for an operator seeing this comparison in the logs
+    pub fn suggested_action(&self) -> &'static str {
+        match self {
+            TipComparison::Matches => "No action needed",
+            TipComparison::Unavailable => {
+                "Oracle is behind the local node; the check resumes once it catches up"
+            }
+            TipComparison::Diverged { .. } => {
+                "Local bitcoin node may be on a bad chain or lagging; compare both hashes on a \
+                 block explorer before trusting swap proofs from this node"
+            }
+        }
+    }
+}
+
+pub async fn compare_tip_with_oracle(
+    oracle: &impl TrustedTipOracle,
+    local_tip: &BlockLeaf,
+) -> eyre::Result<TipComparison> {
+    match oracle.block_hash_at_height(local_tip.height).await? {
+        None => Ok(TipComparison::Unavailable),
+        Some(trusted_block_hash) if trusted_block_hash == local_tip.block_hash => {
+            Ok(TipComparison::Matches)
+        }
+        Some(trusted_block_hash) => Ok(TipComparison::Diverged {
+            height: local_tip.height,
+            local_block_hash: local_tip.block_hash,
+            trusted_block_hash,
+        }),
+    }
+}
+
+/// Deduplicates alerts by signature: a new signature alerts immediately, a repeat of one already
+/// alerted on is suppressed until `interval` has passed since its last alert
+pub struct AlertRateLimiter<K> {
+    interval: Duration,
+    last_alerted: HashMap<K, Instant>,
+}
+
+impl<K: Eq + Hash> AlertRateLimiter<K> {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_alerted: HashMap::new(),
+        }
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Whether an alert for `signature` should be emitted at `now`, recording it if so
+    pub fn should_alert(&mut self, signature: K, now: Instant) -> bool {
+        // signatures not seen for a full interval would alert again anyway, drop them
+        self.last_alerted
+            .retain(|_, alerted_at| now.duration_since(*alerted_at) < self.interval);
+        if self.last_alerted.contains_key(&signature) {
+            return false;
+        }
+        self.last_alerted.insert(signature, now);
+        true
+    }
+}
+
+/// Settings of the trusted tip check, adjustable while it runs through the
+/// `watch::Sender<TipOracleConfig>` it was started with, see
+/// `reload_tip_oracle_config_on_sighup`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TipOracleConfig {
+    /// Interval between checks of the local tip against the oracle
+    pub check_interval: Duration,
+    /// Minimum interval between repeated alerts for the same divergence
+    pub alert_interval: Duration,
+}
+
+/// Settings to change in a running trusted tip check, read from the JSON file passed as
+/// `--trusted-tip-config-file`. Omitted settings keep their current value.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TipOracleConfigUpdate {
+    check_interval_secs: Option<u64>,
+    alert_interval_secs: Option<u64>,
+}
+
+impl TipOracleConfigUpdate {
+    fn apply(&self, config: &mut TipOracleConfig) {
+        if let Some(check_interval_secs) = self.check_interval_secs {
+            config.check_interval = Duration::from_secs(check_interval_secs);
+        }
+        if let Some(alert_interval_secs) = self.alert_interval_secs {
+            config.alert_interval = Duration::from_secs(alert_interval_secs);
+        }
+    }
+}
+
+/// Applies the settings in the JSON file at `path` to the running trusted tip check. A file that
+/// fails to load is logged and the current config kept, a typo shouldn't stop the hypernode.
+fn reload_tip_oracle_config(path: &Path, config_tx: &watch::Sender<TipOracleConfig>) {
+    let update = std::fs::read_to_string(path)
+        .map_err(eyre::Report::from)
+        .and_then(|contents| Ok(serde_json::from_str::<TipOracleConfigUpdate>(&contents)?));
+    match update {
+        Ok(update) => {
+            config_tx.send_modify(|config| update.apply(config));
+            info!(
+                message = "Reloaded trusted tip check config",
+                config = ?*config_tx.borrow()
+            );
+        }
+        Err(e) => warn!(
+            message = "Failed to reload trusted tip check config, keeping the current config",
+            path = %path.display(),
+            error = %e
+        ),
+    }
+}
+
+/// Reloads the trusted tip check's config from `path` on every SIGHUP, letting operators retune
+/// the check during an incident without restarting the hypernode
+pub async fn reload_tip_oracle_config_on_sighup(
+    path: PathBuf,
+    config_tx: watch::Sender<TipOracleConfig>,
+) -> eyre::Result<()> {
+    let mut hangups = signal(SignalKind::hangup())?;
+    while hangups.recv().await.is_some() {
+        reload_tip_oracle_config(&path, &config_tx);
+    }
+    Ok(())
+}
+
+/// Waits until `check_interval` after `last_check` (immediately if there was none) and returns
+/// the config to run the next check with. A config update while waiting takes effect right
+/// away, the wait is re-timed from `last_check` with the new interval.
+async fn wait_for_next_check(
+    config_rx: &mut watch::Receiver<TipOracleConfig>,
+    last_check: Option<tokio::time::Instant>,
+) -> TipOracleConfig {
+    loop {
+        let config = *config_rx.borrow_and_update();
+        let Some(last_check) = last_check else {
+            return config;
+        };
+        tokio::select! {
+            _ = tokio::time::sleep_until(last_check + config.check_interval) => return config,
+            // once every sender is dropped this branch is disabled and the config is fixed
+            Ok(()) = config_rx.changed() => {}
+        }
+    }
+}
+
+/// Periodically cross-checks the bitcoin data engine's tip against `oracle`, alerting on
+/// divergence. The same divergence (by local and trusted block hash) alerts at most once per
+/// alert interval. Oracle failures are logged and retried on the next interval rather than
+/// treated as fatal, the oracle is a secondary source.
+pub async fn run_tip_oracle_check(
+    oracle: impl TrustedTipOracle + 'static,
+    bitcoin_data_engine: Arc<BitcoinDataEngine>,
+    mut config_rx: watch::Receiver<TipOracleConfig>,
+) -> eyre::Result<()> {
+    let mut alert_limiter = AlertRateLimiter::new(config_rx.borrow().alert_interval);
+    let mut last_check = None;
+    loop {
+        let config = wait_for_next_check(&mut config_rx, last_check).await;
+        last_check = Some(tokio::time::Instant::now());
+        alert_limiter.set_interval(config.alert_interval);
+        let Some(local_tip) = bitcoin_data_engine.get_tip_leaf().await? else {
+            continue;
+        };
+        match compare_tip_with_oracle(&oracle, &local_tip).await {
+            Ok(TipComparison::Matches) => {
+                info!(
+                    height = local_tip.height,
+                    "Local bitcoin tip matches trusted oracle"
+                )
+            }
+            Ok(TipComparison::Unavailable) => {}
+            Ok(
+                comparison @ TipComparison::Diverged {
+                    height,
+                    local_block_hash,
+                    trusted_block_hash,
+                },
+            ) => {
+                if alert_limiter
+                    .should_alert((local_block_hash, trusted_block_hash), Instant::now())
+                {
+                    error!(
+                        message = "Local bitcoin tip diverges from trusted oracle",
+                        height,
+                        local_block_hash = %hex::encode(local_block_hash),
+                        trusted_block_hash = %hex::encode(trusted_block_hash),
+                        suggested_action = comparison.suggested_action()
+                    )
+                }
+            }
+            Err(e) => warn!(message = "Failed to query trusted tip oracle", error = %e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedOracle(Option<[u8; 32]>);
+
+    impl TrustedTipOracle for FixedOracle {
+        async fn block_hash_at_height(&self, _height: u32) -> eyre::Result<Option<[u8; 32]>> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_interval_updated_at_runtime() {
+        let (config_tx, mut config_rx) = watch::channel(TipOracleConfig {
+            check_interval: Duration::from_secs(3600),
+            alert_interval: Duration::from_secs(600),
+        });
+
+        // the first check runs immediately
+        assert_eq!(
+            wait_for_next_check(&mut config_rx, None)
+                .await
+                .check_interval,
+            Duration::from_secs(3600)
+        );
+
+        // shortening the interval mid-wait picks up the new cadence without a restart
+        let last_check = tokio::time::Instant::now();
+        let wait = tokio::spawn(async move {
+            let config = wait_for_next_check(&mut config_rx, Some(last_check)).await;
+            (config, config_rx)
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        config_tx.send_modify(|config| config.check_interval = Duration::from_millis(50));
+        let (config, mut config_rx) = tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .expect("Check did not pick up the new interval")
+            .unwrap();
+        assert_eq!(config.check_interval, Duration::from_millis(50));
+        assert!(last_check.elapsed() >= Duration::from_millis(50));
+
+        // with the sender gone the last config keeps applying
+        drop(config_tx);
+        let last_check = tokio::time::Instant::now();
+        wait_for_next_check(&mut config_rx, Some(last_check)).await;
+        assert!(last_check.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_reload_tip_oracle_config() {
+        let initial = TipOracleConfig {
+            check_interval: Duration::from_secs(60),
+            alert_interval: Duration::from_secs(600),
+        };
+        let (config_tx, mut config_rx) = watch::channel(initial);
+        let path =
+            std::env::temp_dir().join(format!("tip-oracle-config-{}.json", std::process::id()));
+
+        // only the settings in the file change
+        std::fs::write(&path, r#"{"check_interval_secs": 5}"#).unwrap();
+        reload_tip_oracle_config(&path, &config_tx);
+        assert!(config_rx.has_changed().unwrap());
+        assert_eq!(
+            *config_rx.borrow_and_update(),
+            TipOracleConfig {
+                check_interval: Duration::from_secs(5),
+                alert_interval: Duration::from_secs(600),
+            }
+        );
+
+        // a malformed or missing file keeps the current config
+        std::fs::write(&path, r#"{"check_interval": 1}"#).unwrap();
+        reload_tip_oracle_config(&path, &config_tx);
+        std::fs::remove_file(&path).unwrap();
+        reload_tip_oracle_config(&path, &config_tx);
+        assert!(!config_rx.has_changed().unwrap());
+        assert_eq!(config_rx.borrow().check_interval, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_repeated_divergence_alerts_once_per_interval() {
+        let mut limiter = AlertRateLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let divergence = ([1u8; 32], [2u8; 32]);
+
+        assert!(limiter.should_alert(divergence, start));
+        for secs in 1..60 {
+            assert!(!limiter.should_alert(divergence, start + Duration::from_secs(secs)));
+        }
+        // a different divergence alerts immediately
+        assert!(limiter.should_alert(([3u8; 32], [2u8; 32]), start + Duration::from_secs(30)));
+
+        // the unresolved divergence alerts again once the interval has passed
+        assert!(limiter.should_alert(divergence, start + Duration::from_secs(60)));
+        assert!(!limiter.should_alert(divergence, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_suggested_action() {
+        assert_eq!(
+            TipComparison::Matches.suggested_action(),
+            "No action needed"
+        );
+        assert!(TipComparison::Unavailable
+            .suggested_action()
+            .contains("Oracle is behind"));
+        assert!(TipComparison::Diverged {
+            height: 100,
+            local_block_hash: [1; 32],
+            trusted_block_hash: [2; 32],
+        }
+        .suggested_action()
+        .contains("bad chain"));
+    }
+
+    #[tokio::test]
+    async fn test_compare_tip_with_oracle() {
+        let local_tip = BlockLeaf::new([1; 32], 100, [0; 32]);
+
+        assert_eq!(
+            compare_tip_with_oracle(&FixedOracle(Some([1; 32])), &local_tip)
+                .await
+                .unwrap(),
+            TipComparison::Matches
+        );
+        assert_eq!(
+            compare_tip_with_oracle(&FixedOracle(None), &local_tip)
+                .await
+                .unwrap(),
+            TipComparison::Unavailable
+        );
+        assert_eq!(
+            compare_tip_with_oracle(&FixedOracle(Some([2; 32])), &local_tip)
+                .await
+                .unwrap(),
+            TipComparison::Diverged {
+                height: 100,
+                local_block_hash: [1; 32],
+                trusted_block_hash: [2; 32],
+            }
+        );
+    }
+}
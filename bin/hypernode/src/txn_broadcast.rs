@@ -1,15 +1,16 @@
 use alloy::{
     eips::BlockId,
-    primitives::Bytes,
+    primitives::{Bytes, U256},
     providers::{Provider, WalletProvider},
     rpc::{
         json_rpc::ErrorPayload,
         types::{TransactionReceipt, TransactionRequest as AlloyTransactionRequest},
     },
-    transports::RpcError,
+    transports::{RpcError, TransportErrorKind},
 };
 use rift_sdk::WebsocketWalletProvider;
-use std::sync::Arc;
+use sol_bindings::RiftExchange;
+use std::{future::Future, sync::Arc};
 use tokio::{
     sync::{
         mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
@@ -17,6 +18,7 @@ use tokio::{
     },
     task::JoinSet,
 };
+use tracing::{debug, warn};
 
 #[derive(Debug, Clone)]
 pub struct RevertInfo {
@@ -33,6 +35,27 @@ impl RevertInfo {
     }
 }
 
+/// Reverts that aren't a known `RiftExchange` error can only be identified from the raw revert
+/// data, log it so the error can be tracked down and added to the bindings
+fn log_undecodable_revert(error_payload: &ErrorPayload) {
+    if error_payload
+        .as_decoded_error::<RiftExchange::RiftExchangeErrors>(false)
+        .is_some()
+    {
+        return;
+    }
+    debug!(
+        message = "Could not decode revert",
+        code = error_payload.code,
+        error_message = %error_payload.message,
+        revert_data = %error_payload
+            .as_revert_data()
+            .map(|data| data.to_string())
+            .unwrap_or_default(),
+        raw_data = ?error_payload.data
+    );
+}
+
 #[derive(Debug, Clone)]
 pub enum TransactionExecutionResult {
     Success(Box<TransactionReceipt>),
@@ -58,12 +81,206 @@ impl TransactionExecutionResult {
     }
 }
 
+/// Native token cost in wei actually paid for a mined transaction
+pub fn transaction_cost_wei(receipt: &TransactionReceipt) -> U256 {
+    U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price)
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PreflightCheck {
     Simulate,
     None,
 }
 
+/// Chooses the preflight check for a stream of broadcasts. Simulation is skipped once
+/// `skip_after_successes` consecutive broadcasts have succeeded, and resumes after any failure.
+/// `None` always simulates.
+#[derive(Debug, Clone, Default)]
+pub struct AdaptivePreflight {
+    skip_after_successes: Option<u32>,
+    consecutive_successes: u32,
+}
+
+impl AdaptivePreflight {
+    pub fn new(skip_after_successes: Option<u32>) -> Self {
+        Self {
+            skip_after_successes,
+            consecutive_successes: 0,
+        }
+    }
+
+    pub fn preflight_check(&self) -> PreflightCheck {
+        match self.skip_after_successes {
+            Some(threshold) if self.consecutive_successes >= threshold => PreflightCheck::None,
+            _ => PreflightCheck::Simulate,
+        }
+    }
+
+    pub fn record_outcome(&mut self, succeeded: bool) {
+        if succeeded {
+            self.consecutive_successes = self.consecutive_successes.saturating_add(1);
+        } else {
+            self.consecutive_successes = 0;
+        }
+    }
+}
+
+/// Geth's default maximum transaction size, transactions with larger calldata are rejected by
+/// the mempool of most nodes
+pub const DEFAULT_MAX_CALLDATA_BYTES: usize = 128 * 1024;
+
+/// Rejects calldata that can't fit in a transaction before it's broadcast, since such a
+/// transaction is guaranteed to fail
+pub fn check_calldata_size(calldata: &Bytes, max_calldata_bytes: usize) -> eyre::Result<()> {
+    if calldata.len() > max_calldata_bytes {
+        return Err(eyre::eyre!(
+            "Calldata is {} bytes, exceeding the maximum of {} bytes",
+            calldata.len(),
+            max_calldata_bytes
+        ));
+    }
+    Ok(())
+}
+
+/// Percentage a gas limit or gas price is raised by before retrying a transaction rejected for it
+const GAS_REMEDIATION_BUMP_PERCENT: u128 = 25;
+/// How many times a transaction rejected for gas is remediated and resent before the rejection is
+/// returned
+const GAS_REMEDIATION_RETRIES: usize = 2;
+
+/// Gas related rejections, which need different fixes before the transaction is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasError {
+    /// The gas limit is too low for the transaction to execute, raise the limit
+    OutOfGas,
+    /// The gas price is too low for the node to accept the transaction, raise the price
+    GasPriceTooLow,
+}
+
+/// Classifies a node's error message as a gas error, `None` for any other error
+pub fn classify_gas_error(message: &str) -> Option<GasError> {
+    let message = message.to_lowercase();
+    if message.contains("gas price too low")
+        || message.contains("underpriced")
+        || message.contains("fee too low")
+        || message.contains("less than block base fee")
+    {
+        Some(GasError::GasPriceTooLow)
+    } else if message.contains("out of gas") || message.contains("intrinsic gas too low") {
+        Some(GasError::OutOfGas)
+    } else {
+        None
+    }
+}
+
+impl GasError {
+    /// The value `remediate` raises, if `request` sets it. Unset values are filled by the node,
+    /// the caller has to look them up instead.
+    pub fn current_value(&self, request: &AlloyTransactionRequest) -> Option<u128> {
+        match self {
+            GasError::OutOfGas => request.gas.map(u128::from),
+            GasError::GasPriceTooLow => request.max_fee_per_gas.or(request.gas_price),
+        }
+    }
+
+    /// Raises the gas limit or gas price of `request` from `current`, the value the rejected
+    /// transaction was sent with. EIP-1559 requests have both of their fee caps raised, a request
+    /// setting neither fee field gets a `max_fee_per_gas` rather than a legacy gas price.
+    pub fn remediate(&self, request: &mut AlloyTransactionRequest, current: u128) {
+        let bump = |value: u128| value + value * GAS_REMEDIATION_BUMP_PERCENT / 100;
+        match self {
+            GasError::OutOfGas => {
+                request.gas = Some(u64::try_from(bump(current)).unwrap_or(u64::MAX));
+            }
+            GasError::GasPriceTooLow => {
+                if request.gas_price.is_some() {
+                    request.gas_price = Some(bump(current));
+                } else {
+                    request.max_fee_per_gas = Some(bump(current));
+                    request.max_priority_fee_per_gas = request.max_priority_fee_per_gas.map(bump);
+                }
+            }
+        }
+    }
+}
+
+/// Sends `request` with `send`, retrying rejections as `classify_gas_error` directs: each retry
+/// raises the value the rejection names, looked up with `lookup` when the request left it to the
+/// node. Any other result is returned as is.
+async fn send_with_gas_remediation<T, S, SFut, L, LFut>(
+    request: &mut AlloyTransactionRequest,
+    mut send: S,
+    mut lookup: L,
+) -> Result<T, RpcError<TransportErrorKind>>
+where
+    S: FnMut(AlloyTransactionRequest) -> SFut,
+    SFut: Future<Output = Result<T, RpcError<TransportErrorKind>>>,
+    L: FnMut(GasError) -> LFut,
+    LFut: Future<Output = Result<u128, RpcError<TransportErrorKind>>>,
+{
+    let mut result = send(request.clone()).await;
+    for retry in 1..=GAS_REMEDIATION_RETRIES {
+        let gas_error = match &result {
+            Err(RpcError::ErrorResp(error_payload)) => classify_gas_error(&error_payload.message),
+            _ => None,
+        };
+        let Some(gas_error) = gas_error else {
+            break;
+        };
+        let current = match gas_error.current_value(request) {
+            Some(current) => current,
+            None => match lookup(gas_error).await {
+                Ok(current) => current,
+                Err(e) => {
+                    warn!(
+                        message = "Failed to look up gas value to remediate",
+                        gas_error = ?gas_error,
+                        error = %e
+                    );
+                    break;
+                }
+            },
+        };
+        warn!(
+            message = "Transaction rejected for gas, retrying",
+            gas_error = ?gas_error,
+            retry
+        );
+        gas_error.remediate(request, current);
+        result = send(request.clone()).await;
+    }
+    result
+}
+
+/// Runs `op` against each rpc in order, moving on to the next only when the request failed at
+/// the transport layer (connection dropped, rate limited, timed out). Any response from a node,
+/// including an error response, is returned as is since another node would answer the same.
+async fn with_rpc_failover<P, T, F, Fut>(
+    rpcs: &[P],
+    mut op: F,
+) -> Result<T, RpcError<TransportErrorKind>>
+where
+    P: Clone,
+    F: FnMut(P) -> Fut,
+    Fut: Future<Output = Result<T, RpcError<TransportErrorKind>>>,
+{
+    let mut last_error = None;
+    for (index, rpc) in rpcs.iter().enumerate() {
+        match op(rpc.clone()).await {
+            Err(RpcError::Transport(e)) => {
+                warn!(
+                    message = "EVM rpc unreachable, failing over",
+                    rpc_index = index,
+                    error = %e
+                );
+                last_error = Some(RpcError::Transport(e));
+            }
+            result => return result,
+        }
+    }
+    Err(last_error.unwrap_or_else(|| TransportErrorKind::custom_str("No EVM rpcs configured")))
+}
+
 #[derive(Debug)]
 struct Request {
     calldata: Bytes,
@@ -83,13 +300,27 @@ impl TransactionBroadcaster {
         wallet_rpc: Arc<WebsocketWalletProvider>,
         debug_rpc_url: String,
         join_set: &mut JoinSet<eyre::Result<()>>,
+    ) -> Self {
+        Self::with_fallback_rpcs(wallet_rpc, vec![], debug_rpc_url, join_set)
+    }
+
+    /// Same as `new`, but requests that can't reach `wallet_rpc` are retried against each of
+    /// `fallback_wallet_rpcs` in order
+    pub fn with_fallback_rpcs(
+        wallet_rpc: Arc<WebsocketWalletProvider>,
+        fallback_wallet_rpcs: Vec<Arc<WebsocketWalletProvider>>,
+        debug_rpc_url: String,
+        join_set: &mut JoinSet<eyre::Result<()>>,
     ) -> Self {
         // Channel is important, here b/c nonce management is difficult and basically impossible to do concurrently - would love for this to not be true
         let (request_sender, request_receiver) = unbounded_channel();
 
+        let mut wallet_rpcs = vec![wallet_rpc];
+        wallet_rpcs.extend(fallback_wallet_rpcs);
+
         // This never exits even if channel is empty, only if channel breaks/closes
         join_set.spawn(async move {
-            Self::broadcast_queue(wallet_rpc, request_receiver, debug_rpc_url).await
+            Self::broadcast_queue(wallet_rpcs, request_receiver, debug_rpc_url).await
         });
 
         Self { request_sender }
@@ -141,11 +372,11 @@ impl TransactionBroadcaster {
     //    - For any other errors: Return the specific error decoded from the receipt
     // Open question, how to type safely return the receipt?
     async fn broadcast_queue(
-        wallet_rpc: Arc<WebsocketWalletProvider>,
+        wallet_rpcs: Vec<Arc<WebsocketWalletProvider>>,
         mut request_receiver: UnboundedReceiver<Request>,
         debug_rpc_url: String,
     ) -> eyre::Result<()> {
-        let signer_address = wallet_rpc.default_signer_address();
+        let signer_address = wallet_rpcs[0].default_signer_address();
         loop {
             let request = match request_receiver.recv().await {
                 Some(req) => req,
@@ -157,7 +388,10 @@ impl TransactionBroadcaster {
             let mut transaction_request = request.transaction_request.clone();
             transaction_request.from = Some(signer_address);
 
-            let block_height = wallet_rpc.get_block_number().await?;
+            let block_height = with_rpc_failover(&wallet_rpcs, |wallet_rpc| async move {
+                wallet_rpc.get_block_number().await
+            })
+            .await?;
             let debug_cli_command = format!(
                 "cast call {} --from {} --data {} --trace --block {} --rpc-url {}",
                 transaction_request.to.unwrap().to().unwrap(), // TODO: Handle contract creation, (error out)
@@ -168,13 +402,20 @@ impl TransactionBroadcaster {
             );
             match request.preflight_check {
                 PreflightCheck::Simulate => {
-                    let simulation_result = wallet_rpc
-                        .call(&transaction_request)
-                        .block(BlockId::Number(block_height.into()))
-                        .await;
+                    let simulation_result = with_rpc_failover(&wallet_rpcs, |wallet_rpc| {
+                        let transaction_request = transaction_request.clone();
+                        async move {
+                            wallet_rpc
+                                .call(&transaction_request)
+                                .block(BlockId::Number(block_height.into()))
+                                .await
+                        }
+                    })
+                    .await;
 
                     let sim_error = match simulation_result.as_ref().err() {
                         Some(RpcError::ErrorResp(error_payload)) => {
+                            log_undecodable_revert(error_payload);
                             Some(TransactionExecutionResult::Revert(RevertInfo::new(
                                 error_payload.to_owned(),
                                 debug_cli_command.clone(),
@@ -205,9 +446,37 @@ impl TransactionBroadcaster {
             }
 
             // Send TXN
-            let txn_result = wallet_rpc
-                .send_transaction(request.transaction_request)
-                .await;
+            let mut send_request = request.transaction_request.clone();
+            let txn_result = send_with_gas_remediation(
+                &mut send_request,
+                |send_request| {
+                    with_rpc_failover(&wallet_rpcs, move |wallet_rpc| {
+                        let transaction_request = send_request.clone();
+                        async move { wallet_rpc.send_transaction(transaction_request).await }
+                    })
+                },
+                |gas_error| {
+                    let wallet_rpcs = &wallet_rpcs;
+                    let transaction_request = &transaction_request;
+                    async move {
+                        match gas_error {
+                            GasError::OutOfGas => with_rpc_failover(wallet_rpcs, |wallet_rpc| {
+                                let transaction_request = transaction_request.clone();
+                                async move { wallet_rpc.estimate_gas(&transaction_request).await }
+                            })
+                            .await
+                            .map(u128::from),
+                            GasError::GasPriceTooLow => {
+                                with_rpc_failover(wallet_rpcs, |wallet_rpc| async move {
+                                    wallet_rpc.get_gas_price().await
+                                })
+                                .await
+                            }
+                        }
+                    }
+                },
+            )
+            .await;
 
             let txn_result = match txn_result {
                 Ok(tx_broadcast) => {
@@ -219,9 +488,13 @@ impl TransactionBroadcaster {
                     }
                 }
                 Err(e) => match e {
-                    RpcError::ErrorResp(error_payload) => TransactionExecutionResult::Revert(
-                        RevertInfo::new(error_payload.to_owned(), debug_cli_command),
-                    ),
+                    RpcError::ErrorResp(error_payload) => {
+                        log_undecodable_revert(&error_payload);
+                        TransactionExecutionResult::Revert(RevertInfo::new(
+                            error_payload.to_owned(),
+                            debug_cli_command,
+                        ))
+                    }
                     _ => TransactionExecutionResult::UnknownError(e.to_string()),
                 },
             };
@@ -234,3 +507,291 @@ impl TransactionBroadcaster {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct EventRecorder(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for EventRecorder {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Fields<'a>(&'a mut String);
+            impl tracing::field::Visit for Fields<'_> {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    self.0.push_str(&format!("{}={:?} ", field.name(), value));
+                }
+            }
+            let mut fields = String::new();
+            event.record(&mut Fields(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+
+    #[test]
+    fn test_undecodable_revert_logs_raw_data() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(EventRecorder(events.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let error_payload: ErrorPayload = serde_json::from_value(serde_json::json!({
+            "code": 3,
+            "message": "execution reverted",
+            "data": "0xdeadbeef"
+        }))
+        .unwrap();
+        log_undecodable_revert(&error_payload);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains("Could not decode revert"));
+        assert!(events[0].contains("revert_data=0xdeadbeef"));
+    }
+
+    #[tokio::test]
+    async fn test_with_rpc_failover_retries_on_fallback() {
+        let attempted = Mutex::new(vec![]);
+        let result = with_rpc_failover(&[0, 1, 2], |rpc| {
+            attempted.lock().unwrap().push(rpc);
+            async move {
+                if rpc == 0 {
+                    Err(TransportErrorKind::custom_str("primary down"))
+                } else {
+                    Ok(rpc)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(*attempted.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_with_rpc_failover_returns_node_errors_without_failover() {
+        let attempted = Mutex::new(vec![]);
+        let result: Result<(), _> = with_rpc_failover(&[0, 1], |rpc| {
+            attempted.lock().unwrap().push(rpc);
+            async move { Err(RpcError::NullResp) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(RpcError::NullResp)));
+        assert_eq!(*attempted.lock().unwrap(), vec![0]);
+
+        let result: Result<(), _> = with_rpc_failover(&[0, 1], |_| async move {
+            Err(TransportErrorKind::custom_str("down"))
+        })
+        .await;
+        assert!(matches!(result, Err(RpcError::Transport(_))));
+    }
+
+    #[test]
+    fn test_gas_errors_classified_with_distinct_remediations() {
+        let out_of_gas = classify_gas_error("execution reverted: out of gas");
+        let price_too_low = classify_gas_error("transaction gas price too low to replace");
+        assert_eq!(out_of_gas, Some(GasError::OutOfGas));
+        assert_eq!(price_too_low, Some(GasError::GasPriceTooLow));
+        assert_eq!(
+            classify_gas_error("replacement transaction underpriced"),
+            Some(GasError::GasPriceTooLow)
+        );
+        assert_eq!(classify_gas_error("nonce too low"), None);
+        // a revert during gas estimation, raising the limit won't help
+        assert_eq!(
+            classify_gas_error("gas required exceeds allowance (30000000)"),
+            None
+        );
+
+        // out of gas raises only the limit
+        let mut request = AlloyTransactionRequest {
+            gas: Some(100_000),
+            gas_price: Some(1_000),
+            ..Default::default()
+        };
+        let current = GasError::OutOfGas.current_value(&request).unwrap();
+        GasError::OutOfGas.remediate(&mut request, current);
+        assert_eq!(request.gas, Some(125_000));
+        assert_eq!(request.gas_price, Some(1_000));
+
+        // a low price raises only the price
+        let current = GasError::GasPriceTooLow.current_value(&request).unwrap();
+        GasError::GasPriceTooLow.remediate(&mut request, current);
+        assert_eq!(request.gas, Some(125_000));
+        assert_eq!(request.gas_price, Some(1_250));
+
+        // EIP-1559 requests have both fee caps raised, unset values come from the node
+        let mut request = AlloyTransactionRequest {
+            max_fee_per_gas: Some(2_000),
+            max_priority_fee_per_gas: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(GasError::OutOfGas.current_value(&request), None);
+        GasError::GasPriceTooLow.remediate(&mut request, 2_000);
+        assert_eq!(request.max_fee_per_gas, Some(2_500));
+        assert_eq!(request.max_priority_fee_per_gas, Some(125));
+        assert_eq!(request.gas_price, None);
+
+        // a request leaving the fees to the node is sent as EIP-1559 with a raised fee cap
+        let mut request = AlloyTransactionRequest::default();
+        assert_eq!(GasError::GasPriceTooLow.current_value(&request), None);
+        GasError::GasPriceTooLow.remediate(&mut request, 2_000);
+        assert_eq!(request.max_fee_per_gas, Some(2_500));
+        assert_eq!(request.gas_price, None);
+    }
+
+    fn rejection(message: &str) -> RpcError<TransportErrorKind> {
+        RpcError::ErrorResp(
+            serde_json::from_value(serde_json::json!({ "code": -32000, "message": message }))
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_with_gas_remediation_follows_classification() {
+        // each rejection raises only the value it's classified as, unset values come from the node
+        let mut rejections = vec![
+            rejection("transaction underpriced"),
+            rejection("intrinsic gas too low"),
+        ]
+        .into_iter();
+        let sent = Mutex::new(vec![]);
+        let lookups = Mutex::new(vec![]);
+        let mut request = AlloyTransactionRequest {
+            gas: Some(100_000),
+            ..Default::default()
+        };
+        let result = send_with_gas_remediation(
+            &mut request,
+            |request| {
+                sent.lock().unwrap().push(request);
+                let result = rejections.next().map_or(Ok(()), Err);
+                async move { result }
+            },
+            |gas_error| {
+                lookups.lock().unwrap().push(gas_error);
+                async { Ok(2_000) }
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(*lookups.lock().unwrap(), vec![GasError::GasPriceTooLow]);
+        let sent = sent.into_inner().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(
+            (sent[1].gas, sent[1].max_fee_per_gas),
+            (Some(100_000), Some(2_500))
+        );
+        assert_eq!(
+            (sent[2].gas, sent[2].max_fee_per_gas),
+            (Some(125_000), Some(2_500))
+        );
+
+        // other errors are returned without a retry
+        let mut sends = 0;
+        let result: Result<(), _> = send_with_gas_remediation(
+            &mut AlloyTransactionRequest::default(),
+            |_| {
+                sends += 1;
+                async { Err(rejection("nonce too low")) }
+            },
+            |_| async { Ok(2_000) },
+        )
+        .await;
+        assert!(matches!(result, Err(RpcError::ErrorResp(_))));
+        assert_eq!(sends, 1);
+
+        // persistent gas rejections are returned once the retries are used up
+        let mut sends = 0;
+        let mut request = AlloyTransactionRequest {
+            gas_price: Some(1_000),
+            ..Default::default()
+        };
+        let result: Result<(), _> = send_with_gas_remediation(
+            &mut request,
+            |_| {
+                sends += 1;
+                async { Err(rejection("fee too low")) }
+            },
+            |_| async { Ok(2_000) },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(sends, 1 + GAS_REMEDIATION_RETRIES);
+        assert_eq!(request.gas_price, Some(1_562));
+    }
+
+    #[test]
+    fn test_transaction_cost_wei() {
+        let receipt: TransactionReceipt = serde_json::from_value(serde_json::json!({
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "transactionIndex": "0x0",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "blockNumber": "0x1",
+            "from": "0x0000000000000000000000000000000000000003",
+            "to": "0x0000000000000000000000000000000000000004",
+            "cumulativeGasUsed": "0x30d40",
+            "gasUsed": "0x30d40",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "type": "0x2",
+            "status": "0x1"
+        }))
+        .unwrap();
+
+        // 200,000 gas at 1 gwei
+        assert_eq!(
+            transaction_cost_wei(&receipt),
+            U256::from(200_000u64 * 1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_check_calldata_size() {
+        assert!(check_calldata_size(&Bytes::from(vec![0u8; 100]), 100).is_ok());
+
+        let err = check_calldata_size(&Bytes::from(vec![0u8; 101]), 100).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Calldata is 101 bytes, exceeding the maximum of 100 bytes"
+        );
+    }
+
+    #[test]
+    fn test_adaptive_preflight_skips_simulation_during_success_streak() {
+        let mut preflight = AdaptivePreflight::new(Some(3));
+
+        for _ in 0..3 {
+            assert_eq!(preflight.preflight_check(), PreflightCheck::Simulate);
+            preflight.record_outcome(true);
+        }
+        assert_eq!(preflight.preflight_check(), PreflightCheck::None);
+        preflight.record_outcome(true);
+        assert_eq!(preflight.preflight_check(), PreflightCheck::None);
+
+        preflight.record_outcome(false);
+        assert_eq!(preflight.preflight_check(), PreflightCheck::Simulate);
+    }
+
+    #[test]
+    fn test_adaptive_preflight_disabled_always_simulates() {
+        let mut preflight = AdaptivePreflight::new(None);
+        for _ in 0..10 {
+            preflight.record_outcome(true);
+        }
+        assert_eq!(preflight.preflight_check(), PreflightCheck::Simulate);
+    }
+}
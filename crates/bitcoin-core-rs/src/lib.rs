@@ -13,9 +13,9 @@ use std::convert::TryInto;
 pub const POW_LIMIT: U256 =
     U256::from_be_hex("00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
 
-const DIFFICULTY_ADJUSTMENT_INTERVAL: u32 = 2016;
+pub const DIFFICULTY_ADJUSTMENT_INTERVAL: u32 = 2016;
 
-const TARGET_BLOCK_TIME: u32 = 1209600; // 2 weeks
+pub const TARGET_BLOCK_TIME: u32 = 1209600; // 2 weeks
 
 trait U256Ext {
     // Convert U256 -> 128-bit pair
@@ -83,7 +83,7 @@ fn target_to_bits(target: U256) -> [u8; 4] {
 }
 
 // modified from https://github.com/rust-bitcoin/rust-bitcoin
-fn bits_to_target(bits: &[u8; 4]) -> U256 {
+pub fn bits_to_target(bits: &[u8; 4]) -> U256 {
     let bits = u32::from_le_bytes(*bits);
     let (mant, expt) = {
         let unshifted_expt = bits >> 24;
@@ -132,6 +132,11 @@ pub fn check_proof_of_work(header: &[u8; 80]) -> bool {
     // step 2: pass array to convert the compressed target into fully expanded form
     let target = bits_to_target(&bit_bytes);
 
+    check_proof_of_work_with_target(header, &target)
+}
+
+// Same as `check_proof_of_work`, for callers that already decoded the header's target
+pub fn check_proof_of_work_with_target(header: &[u8; 80], target: &U256) -> bool {
     // step 3: Calculate the hash of the header
     let hash = get_block_hash(header);
 
@@ -141,7 +146,7 @@ pub fn check_proof_of_work(header: &[u8; 80]) -> bool {
         let hash_int = U256::from_le_slice(&value); // reverse order
 
         // step 4: Compare the hash to the target
-        hash_int <= target
+        hash_int <= *target
     } else {
         false
     }
@@ -155,30 +160,63 @@ pub fn get_retarget_height(height: u32) -> u32 {
     (height - 1) / DIFFICULTY_ADJUSTMENT_INTERVAL * DIFFICULTY_ADJUSTMENT_INTERVAL
 }
 
+// Whether the block at `height` starts a difficulty period of `retarget_interval` blocks
+pub fn is_retarget_height(height: u32, retarget_interval: u32) -> bool {
+    height % retarget_interval == 0
+}
+
 // calculates the new retarget
-fn calculate_next_work_required(
+pub fn calculate_next_work_required(
+    last_retarget_header: &[u8; 80],
+    previous_header: &[u8; 80],
+) -> [u8; 4] {
+    calculate_next_work_required_with_limit(last_retarget_header, previous_header, &POW_LIMIT)
+}
+
+// Same as `calculate_next_work_required`, capping the new target at `pow_limit` instead of
+// mainnet's `POW_LIMIT`
+pub fn calculate_next_work_required_with_limit(
     last_retarget_header: &[u8; 80],
     previous_header: &[u8; 80],
+    pow_limit: &U256,
 ) -> [u8; 4] {
+    calculate_next_work_required_with_params(
+        last_retarget_header,
+        previous_header,
+        pow_limit,
+        DIFFICULTY_ADJUSTMENT_INTERVAL,
+    )
+}
+
+// Same as `calculate_next_work_required_with_limit`, for chains that retarget every
+// `retarget_interval` blocks. The target timespan scales with the interval, keeping mainnet's
+// block spacing.
+pub fn calculate_next_work_required_with_params(
+    last_retarget_header: &[u8; 80],
+    previous_header: &[u8; 80],
+    pow_limit: &U256,
+    retarget_interval: u32,
+) -> [u8; 4] {
+    let target_timespan = retarget_interval * (TARGET_BLOCK_TIME / DIFFICULTY_ADJUSTMENT_INTERVAL);
+
     // Limit adjustment step
     let mut timespan = previous_header.time() - last_retarget_header.time();
-    if timespan < TARGET_BLOCK_TIME / 4 {
-        timespan = TARGET_BLOCK_TIME / 4;
+    if timespan < target_timespan / 4 {
+        timespan = target_timespan / 4;
     }
-    if timespan > TARGET_BLOCK_TIME * 4 {
-        timespan = TARGET_BLOCK_TIME * 4;
+    if timespan > target_timespan * 4 {
+        timespan = target_timespan * 4;
     }
 
-    let pow_limit = POW_LIMIT;
     let mut new_target = bits_to_target(&last_retarget_header.bits());
 
     new_target = new_target.wrapping_mul(&U256::from(timespan));
     new_target = new_target
-        .checked_div(&U256::from(TARGET_BLOCK_TIME))
+        .checked_div(&U256::from(target_timespan))
         .expect("Division succeeds");
 
-    if new_target > pow_limit {
-        new_target = pow_limit;
+    if new_target > *pow_limit {
+        new_target = *pow_limit;
     }
 
     target_to_bits(new_target)
@@ -190,11 +228,52 @@ pub fn validate_next_work_required(
     previous_header: &[u8; 80],
     current_header: &[u8; 80],
 ) -> Result<[u8; 80]> {
-    if (previous_height + 1) % DIFFICULTY_ADJUSTMENT_INTERVAL == 0 {
+    validate_next_work_required_with_limit(
+        last_retarget_header,
+        previous_height,
+        previous_header,
+        current_header,
+        &POW_LIMIT,
+    )
+}
+
+// Same as `validate_next_work_required`, for chains with a PoW limit other than mainnet's
+pub fn validate_next_work_required_with_limit(
+    last_retarget_header: &[u8; 80],
+    previous_height: u32,
+    previous_header: &[u8; 80],
+    current_header: &[u8; 80],
+    pow_limit: &U256,
+) -> Result<[u8; 80]> {
+    validate_next_work_required_with_params(
+        last_retarget_header,
+        previous_height,
+        previous_header,
+        current_header,
+        pow_limit,
+        DIFFICULTY_ADJUSTMENT_INTERVAL,
+    )
+}
+
+// Same as `validate_next_work_required_with_limit`, for chains that retarget every
+// `retarget_interval` blocks instead of mainnet's `DIFFICULTY_ADJUSTMENT_INTERVAL`
+pub fn validate_next_work_required_with_params(
+    last_retarget_header: &[u8; 80],
+    previous_height: u32,
+    previous_header: &[u8; 80],
+    current_header: &[u8; 80],
+    pow_limit: &U256,
+    retarget_interval: u32,
+) -> Result<[u8; 80]> {
+    if is_retarget_height(previous_height + 1, retarget_interval) {
         let current_header_difficulty = current_header.bits();
 
-        let calculated_difficulty =
-            calculate_next_work_required(last_retarget_header, previous_header);
+        let calculated_difficulty = calculate_next_work_required_with_params(
+            last_retarget_header,
+            previous_header,
+            pow_limit,
+            retarget_interval,
+        );
 
         if current_header_difficulty == calculated_difficulty {
             Ok(*current_header)
@@ -301,6 +380,16 @@ mod tests {
         assert_eq!(hash, expected_hash);
     }
 
+    #[test]
+    fn test_retarget_heights_with_custom_interval() {
+        assert!(is_retarget_height(0, 144));
+        assert!(!is_retarget_height(143, 144));
+        assert!(is_retarget_height(144, 144));
+        assert!(is_retarget_height(288, 144));
+        assert!(!is_retarget_height(144, DIFFICULTY_ADJUSTMENT_INTERVAL));
+        assert!(is_retarget_height(2016, DIFFICULTY_ADJUSTMENT_INTERVAL));
+    }
+
     #[test]
     fn test_get_retarget_height() {
         assert_eq!(get_retarget_height(0), 0);
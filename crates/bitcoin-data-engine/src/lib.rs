@@ -109,26 +109,22 @@ impl BitcoinDataEngine {
             }
         }
     }
+
+    /// Returns the tip of the local MMR, read under a single lock so the leaf count
+    /// and tip leaf can't be torn by a concurrent reorg.
+    pub async fn get_tip_leaf(&self) -> eyre::Result<Option<BlockLeaf>> {
+        Ok(self.indexed_mmr.read().await.get_tip_leaf().await?)
+    }
 }
 
 /// Helper function to retrieve the local tip's hash and the total leaf count
 async fn get_local_tip(
     mmr: &RwLock<IndexedMMR<Keccak256Hasher>>,
 ) -> eyre::Result<(Option<Digest>, u32)> {
-    let local_indexed_mmr = mmr.read().await;
-    let leaf_count = local_indexed_mmr.get_leaf_count().await?;
-
-    if leaf_count == 0 {
-        return Ok((None, 0));
+    match mmr.read().await.get_tip_leaf().await? {
+        Some(tip_leaf) => Ok((Some(tip_leaf.block_hash), tip_leaf.height + 1)),
+        None => Ok((None, 0)),
     }
-
-    let tip_block_hash = local_indexed_mmr
-        .get_leaf_by_leaf_index(leaf_count - 1)
-        .await?
-        .ok_or_else(|| eyre::eyre!("Failed to get tip leaf"))?
-        .block_hash;
-
-    Ok((Some(tip_block_hash), leaf_count as u32))
 }
 
 /// This function is responsible for re-syncing the local MMR with the remote node's best chain
@@ -5,7 +5,9 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::hasher::Hasher;
-use crate::light_client::Header;
+use crate::light_client::{
+    calculate_cumulative_work, try_validate_header_chain, Header, HeaderChainError,
+};
 
 #[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize, Default)]
 pub struct BlockLeaf {
@@ -14,11 +16,10 @@ pub struct BlockLeaf {
     pub cumulative_chainwork: [u8; 32], // Stored in reverse byte order
 }
 
+// The onchain leaf shares the same field widths (bytes32, uint32, uint256), so conversions
+// in both directions are lossless. Chainwork bytes are big endian, matching `chainwork_as_u256`.
 impl From<BlockLeaf> for sol_bindings::Types::BlockLeaf {
     fn from(leaf: BlockLeaf) -> Self {
-        // Chainwork is stored in reverse byte order, so we need to reverse it before converting to the Uint type
-        let mut cumulative_chainwork = leaf.cumulative_chainwork;
-        cumulative_chainwork.reverse();
         sol_bindings::Types::BlockLeaf {
             height: leaf.height,
             blockHash: leaf.block_hash.into(),
@@ -29,6 +30,16 @@ impl From<BlockLeaf> for sol_bindings::Types::BlockLeaf {
     }
 }
 
+impl From<sol_bindings::Types::BlockLeaf> for BlockLeaf {
+    fn from(leaf: sol_bindings::Types::BlockLeaf) -> Self {
+        BlockLeaf::new(
+            leaf.blockHash.0,
+            leaf.height,
+            leaf.cumulativeChainwork.to_be_bytes::<32>(),
+        )
+    }
+}
+
 const SERIALIZED_LEAF_SIZE: usize = 68;
 
 impl BlockLeaf {
@@ -46,6 +57,21 @@ impl BlockLeaf {
         U256::from_be_bytes(self.cumulative_chainwork)
     }
 
+    // Same as `chainwork_as_u256`, but rejects chainwork a valid chain can't have at this height.
+    // Every block contributes at least one unit of work, so a leaf at height h must carry at
+    // least h + 1, catching zeroed or corrupted leaves from a bad store before they're compared.
+    pub fn checked_chainwork_as_u256(&self) -> Result<U256, String> {
+        let chainwork = self.chainwork_as_u256();
+        if chainwork < U256::from_u64(self.height as u64 + 1) {
+            return Err(format!(
+                "Leaf at height {} has implausible cumulative chainwork 0x{}",
+                self.height,
+                hex::encode(self.cumulative_chainwork)
+            ));
+        }
+        Ok(chainwork)
+    }
+
     pub fn natural_block_hash(&self) -> [u8; 32] {
         let mut natural_block_hash = self.block_hash;
         natural_block_hash.reverse();
@@ -57,6 +83,13 @@ impl BlockLeaf {
         self.natural_block_hash() == *other
     }
 
+    // Checks the leaf's block hash was computed from `header`, catching a leaf and header
+    // that got out of sync between the header store and the MMR
+    pub fn verify_consistency(&self, header: &Header) -> bool {
+        bitcoin_core_rs::get_block_hash(header.as_bytes())
+            .is_ok_and(|block_hash| self.compare_by_natural_block_hash(&block_hash))
+    }
+
     pub fn hash<H: Hasher>(&self) -> [u8; 32] {
         // Concatenate all fields into a single buffer
         let mut buffer = Vec::with_capacity(32 + 32 + 32);
@@ -144,6 +177,74 @@ pub fn create_new_leaves(
         .collect()
 }
 
+/// Validates `new_headers` as a direct extension of an MMR whose tip is `tip_leaf` (with
+/// `tip_header` and the retarget header of its difficulty period), without revalidating any
+/// of the existing chain. Returns the leaves to append to the MMR.
+pub fn validate_tip_extension(
+    tip_leaf: &BlockLeaf,
+    tip_header: &Header,
+    tip_retarget_header: &Header,
+    new_headers: &[Header],
+) -> Result<Vec<BlockLeaf>, HeaderChainError> {
+    if !tip_leaf.verify_consistency(tip_header) {
+        return Err(HeaderChainError::ParentLeafMismatch {
+            height: tip_leaf.height,
+        });
+    }
+
+    try_validate_header_chain(
+        tip_leaf.height,
+        tip_header,
+        tip_retarget_header,
+        new_headers,
+        None,
+    )?;
+
+    let (new_chain_works, _) = calculate_cumulative_work(tip_leaf.chainwork_as_u256(), new_headers);
+    Ok(create_new_leaves(tip_leaf, new_headers, &new_chain_works))
+}
+
+/// Same as `validate_tip_extension`, for callers that only have the tip's leaf and not its
+/// header. The first new header is linked to the leaf by hash and its difficulty checked
+/// against `tip_retarget_header`, which is trusted to be the retarget header of the tip's
+/// period. A first header that starts a new difficulty period can't be validated this way,
+/// its expected difficulty depends on the tip header's timestamp.
+pub fn validate_tip_extension_from_leaf(
+    tip_leaf: &BlockLeaf,
+    tip_retarget_header: &Header,
+    new_headers: &[Header],
+) -> Result<Vec<BlockLeaf>, HeaderChainError> {
+    let first_header = new_headers.first().ok_or(HeaderChainError::EmptyChain)?;
+    let height = tip_leaf.height + 1;
+
+    if first_header.0[4..36] != tip_leaf.natural_block_hash() {
+        return Err(HeaderChainError::BrokenLink { height });
+    }
+    if height % bitcoin_core_rs::DIFFICULTY_ADJUSTMENT_INTERVAL == 0 {
+        return Err(HeaderChainError::ParentHeaderRequired { height });
+    }
+    if first_header.0[72..76] != tip_retarget_header.0[72..76] {
+        return Err(HeaderChainError::InvalidWorkRequirement { height });
+    }
+    if !bitcoin_core_rs::check_proof_of_work(first_header.as_bytes()) {
+        return Err(HeaderChainError::InvalidProofOfWork { height });
+    }
+
+    // the first header is now a validated parent for the rest of the chain
+    if new_headers.len() > 1 {
+        try_validate_header_chain(
+            height,
+            first_header,
+            tip_retarget_header,
+            &new_headers[1..],
+            None,
+        )?;
+    }
+
+    let (new_chain_works, _) = calculate_cumulative_work(tip_leaf.chainwork_as_u256(), new_headers);
+    Ok(create_new_leaves(tip_leaf, new_headers, &new_chain_works))
+}
+
 pub fn get_genesis_leaf() -> BlockLeaf {
     BlockLeaf::new(
         hex!("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"),
@@ -176,6 +277,7 @@ pub fn decompress_block_leaves(bytes: &[u8]) -> Vec<BlockLeaf> {
 #[cfg(test)]
 mod tests {
     use crate::hasher::Keccak256Hasher;
+    use crate::mmr::CompactMerkleMountainRange;
 
     use super::*;
     use test_data_utils::TEST_HEADERS;
@@ -252,6 +354,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_tip_extension() {
+        let genesis_leaf = get_genesis_leaf();
+        let genesis_header = Header(TEST_HEADERS[0].1);
+        let mut mmr = CompactMerkleMountainRange::<Keccak256Hasher>::new();
+        mmr.append(&genesis_leaf.hash::<Keccak256Hasher>());
+
+        let new_headers: Vec<Header> = TEST_HEADERS[1..=5]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let new_leaves = validate_tip_extension(
+            &genesis_leaf,
+            &genesis_header,
+            &genesis_header,
+            &new_headers,
+        )
+        .unwrap();
+        for leaf in &new_leaves {
+            mmr.append(&leaf.hash::<Keccak256Hasher>());
+        }
+
+        let chainworks: Vec<U256> = (2..=6).map(|n| U256::from_u64(4295032833 * n)).collect();
+        let mut expected_mmr = CompactMerkleMountainRange::<Keccak256Hasher>::new();
+        for leaf in std::iter::once(genesis_leaf).chain(create_new_leaves(
+            &genesis_leaf,
+            &new_headers,
+            &chainworks,
+        )) {
+            expected_mmr.append(&leaf.hash::<Keccak256Hasher>());
+        }
+
+        assert_eq!(new_leaves.last().unwrap().height, 5);
+        assert_eq!(mmr.leaf_count, 6);
+        assert_eq!(mmr.get_root(), expected_mmr.get_root());
+    }
+
+    #[test]
+    fn test_validate_tip_extension_rejects_mismatched_tip_header() {
+        let new_headers: Vec<Header> = TEST_HEADERS[2..=5]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert_eq!(
+            validate_tip_extension(
+                &get_genesis_leaf(),
+                &Header(TEST_HEADERS[1].1),
+                &Header(TEST_HEADERS[0].1),
+                &new_headers
+            ),
+            Err(HeaderChainError::ParentLeafMismatch { height: 0 })
+        );
+    }
+
+    #[test]
+    fn test_validate_tip_extension_from_leaf() {
+        let genesis_leaf = get_genesis_leaf();
+        let genesis_header = Header(TEST_HEADERS[0].1);
+        let new_headers: Vec<Header> = TEST_HEADERS[1..=5]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert_eq!(
+            validate_tip_extension_from_leaf(&genesis_leaf, &genesis_header, &new_headers),
+            validate_tip_extension(
+                &genesis_leaf,
+                &genesis_header,
+                &genesis_header,
+                &new_headers
+            )
+        );
+        assert_eq!(
+            validate_tip_extension_from_leaf(&genesis_leaf, &genesis_header, &new_headers[1..]),
+            Err(HeaderChainError::BrokenLink { height: 1 })
+        );
+
+        let mut tip_hash = bitcoin_core_rs::get_block_hash(&TEST_HEADERS[2015].1).unwrap();
+        tip_hash.reverse();
+        let tip_leaf = BlockLeaf::new(tip_hash, 2015, [0; 32]);
+        assert_eq!(
+            validate_tip_extension_from_leaf(
+                &tip_leaf,
+                &genesis_header,
+                &[Header(TEST_HEADERS[2016].1)]
+            ),
+            Err(HeaderChainError::ParentHeaderRequired { height: 2016 })
+        );
+    }
+
+    #[test]
+    fn test_verify_consistency() {
+        let genesis_leaf = get_genesis_leaf();
+
+        assert!(genesis_leaf.verify_consistency(&Header(TEST_HEADERS[0].1)));
+        assert!(!genesis_leaf.verify_consistency(&Header(TEST_HEADERS[1].1)));
+    }
+
     #[test]
     fn test_chainwork_as_u256() {
         let leaf = get_genesis_leaf();
@@ -260,6 +461,24 @@ mod tests {
 
     // TODO: Compare solidity hasher to rust hasher
 
+    #[test]
+    fn test_checked_chainwork_as_u256() {
+        let leaf = get_genesis_leaf();
+        assert_eq!(
+            leaf.checked_chainwork_as_u256(),
+            Ok(U256::from_u64(4295032833))
+        );
+
+        let mut corrupt_leaf = BlockLeaf::new([1; 32], 10, [0; 32]);
+        assert!(corrupt_leaf.checked_chainwork_as_u256().is_err());
+
+        corrupt_leaf.cumulative_chainwork[31] = 11;
+        assert_eq!(
+            corrupt_leaf.checked_chainwork_as_u256(),
+            Ok(U256::from_u64(11))
+        );
+    }
+
     #[test]
     fn test_block_leaf_hash() {
         let leaf = get_genesis_leaf();
@@ -269,4 +488,25 @@ mod tests {
             hex!("1f05e44006fa2411c12ffefe8397c210c0dd53d73d63d30afb84461df8819a92")
         );
     }
+
+    #[test]
+    fn test_sol_block_leaf_roundtrip() {
+        let leaves = [
+            get_genesis_leaf(),
+            BlockLeaf::new([0xff; 32], u32::MAX, [0xff; 32]),
+            BlockLeaf::new([0x01; 32], 800_000, [0x80; 32]),
+        ];
+
+        for leaf in leaves {
+            let sol_leaf: sol_bindings::Types::BlockLeaf = leaf.into();
+            assert_eq!(sol_leaf.height, leaf.height);
+            assert_eq!(sol_leaf.blockHash.0, leaf.block_hash);
+            assert_eq!(
+                sol_leaf.cumulativeChainwork.to_be_bytes::<32>(),
+                leaf.chainwork_as_u256().to_be_bytes()
+            );
+
+            assert_eq!(BlockLeaf::from(sol_leaf), leaf);
+        }
+    }
 }
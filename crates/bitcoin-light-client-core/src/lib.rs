@@ -12,7 +12,7 @@ use sol_bindings::Types::LightClientPublicInput;
 use crate::hasher::{Digest, Hasher};
 use crate::leaves::create_new_leaves;
 use crate::leaves::{BlockLeaf, BlockLeafCompressor};
-use crate::light_client::Header;
+use crate::light_client::{Header, HeaderChainCheckpoint};
 use crate::mmr::{CompactMerkleMountainRange, MMRProof};
 
 fn validate_leaf_block_hashes(
@@ -151,6 +151,27 @@ pub struct VerifiedBlock {
     pub mmr_data: ProvenLeaf,
 }
 
+impl VerifiedBlock {
+    /// Bundles this block and the retarget block of its difficulty period into a checkpoint
+    /// new headers can be validated against, using the proven leaf's cumulative chainwork.
+    /// Panics if either header does not match its leaf.
+    pub fn header_chain_checkpoint(&self, retarget: &VerifiedBlock) -> HeaderChainCheckpoint {
+        validate_leaf_block_hashes(
+            &self.header,
+            &self.mmr_data.leaf,
+            &retarget.header,
+            &retarget.mmr_data.leaf,
+        );
+
+        HeaderChainCheckpoint {
+            height: self.mmr_data.leaf.height,
+            header: self.header,
+            retarget_header: retarget.header,
+            cumulative_work: self.mmr_data.leaf.chainwork_as_u256(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Default)]
 pub struct ChainTransition {
     // Previous MMR state
@@ -207,6 +228,156 @@ impl fmt::Debug for ChainTransition {
     }
 }
 
+/// The parts of a `ChainTransition` worth logging, see `ChainTransition::summarize`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainTransitionSummary {
+    pub previous_tip: BlockLeaf,
+    pub new_tip_height: u32,
+    /// Display byte order, like `BlockLeaf::block_hash`
+    pub new_tip_hash: [u8; 32],
+    pub new_tip_chainwork: U256,
+    pub appended_leaves: usize,
+    pub disposed_leaves: usize,
+}
+
+impl fmt::Display for ChainTransitionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let previous_tip_chainwork = self.previous_tip.chainwork_as_u256();
+        let (sign, work_delta) = if self.new_tip_chainwork >= previous_tip_chainwork {
+            (
+                "+",
+                self.new_tip_chainwork.wrapping_sub(&previous_tip_chainwork),
+            )
+        } else {
+            (
+                "-",
+                previous_tip_chainwork.wrapping_sub(&self.new_tip_chainwork),
+            )
+        };
+        let work_delta = format!("{:x}", work_delta);
+        write!(
+            f,
+            "tip {} ({}) -> {} ({}), {} appended, {} disposed, work delta {}0x{}",
+            self.previous_tip.height,
+            hex::encode(self.previous_tip.block_hash),
+            self.new_tip_height,
+            hex::encode(self.new_tip_hash),
+            self.appended_leaves,
+            self.disposed_leaves,
+            sign,
+            match work_delta.trim_start_matches('0') {
+                "" => "0",
+                trimmed => trimmed,
+            }
+        )
+    }
+}
+
+/// Why a `ChainTransition` failed `ChainTransition::validate_chainwork_monotonicity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainworkMonotonicityError {
+    ImplausibleLeafChainwork {
+        height: u32,
+        cumulative_chainwork: [u8; 32],
+    },
+    RetargetNotAncestor {
+        retarget_height: u32,
+        parent_height: u32,
+    },
+    NoNewHeaders,
+    HeaderWorkUnavailable {
+        height: u32,
+    },
+    ChainworkNotIncreasing {
+        height: u32,
+    },
+    ChainworkOverflow {
+        height: u32,
+    },
+}
+
+impl fmt::Display for ChainworkMonotonicityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainworkMonotonicityError::ImplausibleLeafChainwork {
+                height,
+                cumulative_chainwork,
+            } => write!(
+                f,
+                "Leaf at height {} has implausible cumulative chainwork 0x{}",
+                height,
+                hex::encode(cumulative_chainwork)
+            ),
+            ChainworkMonotonicityError::RetargetNotAncestor {
+                retarget_height,
+                parent_height,
+            } => write!(
+                f,
+                "Parent retarget leaf at height {} is not an ancestor by work of parent leaf at height {}",
+                retarget_height, parent_height
+            ),
+            ChainworkMonotonicityError::NoNewHeaders => write!(f, "Transition has no new headers"),
+            ChainworkMonotonicityError::HeaderWorkUnavailable { height } => {
+                write!(f, "Failed to compute work of header {}", height)
+            }
+            ChainworkMonotonicityError::ChainworkNotIncreasing { height } => {
+                write!(
+                    f,
+                    "Cumulative chainwork does not increase at height {}",
+                    height
+                )
+            }
+            ChainworkMonotonicityError::ChainworkOverflow { height } => {
+                write!(f, "Cumulative chainwork overflows at height {}", height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainworkMonotonicityError {}
+
+/// Why a `ChainTransition` failed `ChainTransition::validate_consistency`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainTransitionError {
+    ParentLeafMismatch,
+    ParentRetargetLeafMismatch,
+    ChainworkNotMonotonic(ChainworkMonotonicityError),
+    MmrRootMismatch,
+    InvalidInclusionProof { leaf: &'static str, height: u32 },
+}
+
+impl fmt::Display for ChainTransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainTransitionError::ParentLeafMismatch => {
+                write!(f, "Parent header does not match parent leaf")
+            }
+            ChainTransitionError::ParentRetargetLeafMismatch => {
+                write!(
+                    f,
+                    "Parent retarget header does not match parent retarget leaf"
+                )
+            }
+            ChainTransitionError::ChainworkNotMonotonic(error) => write!(f, "{}", error),
+            ChainTransitionError::MmrRootMismatch => {
+                write!(
+                    f,
+                    "Current MMR root does not match the bagged peak and leaf count"
+                )
+            }
+            ChainTransitionError::InvalidInclusionProof { leaf, height } => {
+                write!(
+                    f,
+                    "Invalid inclusion proof for {} leaf at height {}",
+                    leaf, height
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainTransitionError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AuxiliaryLightClientData {
     pub compressed_leaves: Vec<u8>,
@@ -236,6 +407,135 @@ impl ChainTransition {
         }
     }
 
+    /// Cheap sanity check to run before proving: the parent retarget, parent, and each new
+    /// header must have non-decreasing heights and strictly increasing cumulative chainwork
+    /// (the retarget block may be the parent itself), and the parent leaves must carry plausible
+    /// chainwork (see `BlockLeaf::checked_chainwork_as_u256`). Does not validate PoW or MMR proofs.
+    pub fn validate_chainwork_monotonicity(&self) -> Result<(), ChainworkMonotonicityError> {
+        let checked_chainwork = |leaf: &BlockLeaf| {
+            leaf.checked_chainwork_as_u256().map_err(|_| {
+                ChainworkMonotonicityError::ImplausibleLeafChainwork {
+                    height: leaf.height,
+                    cumulative_chainwork: leaf.cumulative_chainwork,
+                }
+            })
+        };
+        let parent_retarget_leaf = &self.parent_retarget.mmr_data.leaf;
+        let parent_leaf = &self.parent.mmr_data.leaf;
+        let parent_retarget_work = checked_chainwork(parent_retarget_leaf)?;
+        let parent_work = checked_chainwork(parent_leaf)?;
+
+        if parent_retarget_leaf.height > parent_leaf.height
+            || parent_retarget_work > parent_work
+            || (parent_retarget_leaf.height == parent_leaf.height
+                && parent_retarget_leaf != parent_leaf)
+        {
+            return Err(ChainworkMonotonicityError::RetargetNotAncestor {
+                retarget_height: parent_retarget_leaf.height,
+                parent_height: parent_leaf.height,
+            });
+        }
+
+        if self.new_headers.is_empty() {
+            return Err(ChainworkMonotonicityError::NoNewHeaders);
+        }
+
+        let mut cumulative_work = parent_work;
+        for (height, header) in (parent_leaf.height + 1..).zip(&self.new_headers) {
+            let header_proof = bitcoin_core_rs::get_block_proof(header.as_bytes())
+                .map_err(|_| ChainworkMonotonicityError::HeaderWorkUnavailable { height })?;
+            let next_work: Option<U256> = cumulative_work
+                .checked_add(&U256::from_le_bytes(header_proof))
+                .into();
+            match next_work {
+                Some(next_work) if next_work > cumulative_work => cumulative_work = next_work,
+                Some(_) => {
+                    return Err(ChainworkMonotonicityError::ChainworkNotIncreasing { height })
+                }
+                None => return Err(ChainworkMonotonicityError::ChainworkOverflow { height }),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Summarizes the tip movement of the transition for logging, without validating it
+    pub fn summarize(&self) -> ChainTransitionSummary {
+        let parent_leaf = &self.parent.mmr_data.leaf;
+        let (new_tip_hash, new_tip_chainwork) = match self.new_headers.last() {
+            Some(new_tip) => {
+                let mut new_tip_hash = bitcoin_core_rs::get_block_hash(new_tip.as_bytes())
+                    .expect("Failed to get new tip block hash");
+                new_tip_hash.reverse();
+                let (_, new_tip_chainwork) = light_client::calculate_cumulative_work(
+                    parent_leaf.chainwork_as_u256(),
+                    &self.new_headers,
+                );
+                (new_tip_hash, new_tip_chainwork)
+            }
+            None => (parent_leaf.block_hash, parent_leaf.chainwork_as_u256()),
+        };
+
+        ChainTransitionSummary {
+            previous_tip: self.current_tip.leaf,
+            new_tip_height: parent_leaf.height + self.new_headers.len() as u32,
+            new_tip_hash,
+            new_tip_chainwork,
+            appended_leaves: self.new_headers.len(),
+            disposed_leaves: self.disposed_leaf_hashes.len(),
+        }
+    }
+
+    /// Non-panicking subset of the checks done by [`ChainTransition::verify`]: the parent and
+    /// parent retarget headers match their leaves, chainwork is monotonic, the current MMR root
+    /// matches the bagged peak, and each proven leaf is included under the current MMR root.
+    /// Does not validate PoW of the new headers.
+    pub fn validate_consistency<H: Hasher>(&self) -> Result<(), ChainTransitionError> {
+        if !self
+            .parent
+            .mmr_data
+            .leaf
+            .verify_consistency(&self.parent.header)
+        {
+            return Err(ChainTransitionError::ParentLeafMismatch);
+        }
+        if !self
+            .parent_retarget
+            .mmr_data
+            .leaf
+            .verify_consistency(&self.parent_retarget.header)
+        {
+            return Err(ChainTransitionError::ParentRetargetLeafMismatch);
+        }
+
+        self.validate_chainwork_monotonicity()
+            .map_err(ChainTransitionError::ChainworkNotMonotonic)?;
+
+        let current_tip_chain_leaf_count = self.current_tip.leaf.height + 1;
+        if mmr::get_root::<H>(current_tip_chain_leaf_count, &self.current_mmr_bagged_peak)
+            != self.current_mmr_root
+        {
+            return Err(ChainTransitionError::MmrRootMismatch);
+        }
+
+        for (leaf, proven_leaf) in [
+            ("parent", &self.parent.mmr_data),
+            ("parent retarget", &self.parent_retarget.mmr_data),
+            ("current tip", &self.current_tip),
+        ] {
+            if proven_leaf.proof.leaf_hash != proven_leaf.leaf.hash::<H>()
+                || !mmr::verify_mmr_proof::<H>(&self.current_mmr_root, &proven_leaf.proof)
+            {
+                return Err(ChainTransitionError::InvalidInclusionProof {
+                    leaf,
+                    height: proven_leaf.leaf.height,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Commit to a new chain, validating the new headers are valid under PoW
     /// and that the new chain extends the previous chain from a previous header.
     /// auxiliary data is used by clients who create proofs who need to post data onchain
@@ -535,6 +835,214 @@ mod tests {
         // Verify the new MMR root and public inputs
     }
 
+    #[test]
+    fn test_validate_header_chain_from_verified_block() {
+        let genesis_leaf = get_genesis_leaf();
+        let headers: Vec<Header> = TEST_HEADERS[0..200]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        let (chain_works, _) = validate_chainwork(&genesis_leaf, &genesis_leaf, &headers[1..=100]);
+        let leaves = create_new_leaves(&genesis_leaf, &headers[1..=100], &chain_works);
+
+        let parent = VerifiedBlock {
+            header: headers[100],
+            mmr_data: ProvenLeaf {
+                leaf: leaves[99],
+                proof: MMRProof::default(),
+            },
+        };
+        let parent_retarget = VerifiedBlock {
+            header: headers[0],
+            mmr_data: ProvenLeaf {
+                leaf: genesis_leaf,
+                proof: MMRProof::default(),
+            },
+        };
+
+        let checkpoint = parent.header_chain_checkpoint(&parent_retarget);
+        assert_eq!(checkpoint.height, 100);
+        assert_eq!(checkpoint.cumulative_work, leaves[99].chainwork_as_u256());
+
+        let (extension_works, extension_final_work) =
+            checkpoint.validate_header_chain(&headers[101..200]);
+
+        let (_, expected_final_work) =
+            validate_chainwork(&genesis_leaf, &genesis_leaf, &headers[1..200]);
+        assert_eq!(extension_works.len(), 99);
+        assert_eq!(extension_final_work, expected_final_work);
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent leaf block hash")]
+    fn test_verified_block_checkpoint_rejects_mismatched_header() {
+        let genesis_leaf = get_genesis_leaf();
+        let parent = VerifiedBlock {
+            header: Header(TEST_HEADERS[1].1),
+            mmr_data: ProvenLeaf {
+                leaf: genesis_leaf,
+                proof: MMRProof::default(),
+            },
+        };
+        let parent_retarget = VerifiedBlock {
+            header: Header(TEST_HEADERS[0].1),
+            mmr_data: ProvenLeaf {
+                leaf: genesis_leaf,
+                proof: MMRProof::default(),
+            },
+        };
+
+        parent.header_chain_checkpoint(&parent_retarget);
+    }
+
+    fn monotonicity_test_transition() -> ChainTransition {
+        let genesis_block = VerifiedBlock {
+            header: Header(TEST_HEADERS[0].1),
+            mmr_data: ProvenLeaf {
+                leaf: get_genesis_leaf(),
+                proof: MMRProof::default(),
+            },
+        };
+        ChainTransition {
+            parent: genesis_block.clone(),
+            parent_retarget: genesis_block,
+            new_headers: TEST_HEADERS[1..10]
+                .iter()
+                .map(|(_, header)| Header(*header))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chain_transition_summarize() {
+        let mut transition = monotonicity_test_transition();
+        transition.current_tip.leaf = get_genesis_leaf();
+
+        let summary = transition.summarize();
+
+        let mut block_9_hash = bitcoin_core_rs::get_block_hash(&TEST_HEADERS[9].1).unwrap();
+        block_9_hash.reverse();
+        assert_eq!(summary.new_tip_height, 9);
+        assert_eq!(summary.new_tip_hash, block_9_hash);
+        assert_eq!(summary.new_tip_chainwork, U256::from_u64(4295032833 * 10));
+        assert_eq!(summary.appended_leaves, 9);
+        assert_eq!(summary.disposed_leaves, 0);
+        assert_eq!(
+            summary.to_string(),
+            format!(
+                "tip 0 ({}) -> 9 ({}), 9 appended, 0 disposed, work delta +0x900090009",
+                hex::encode(get_genesis_leaf().block_hash),
+                hex::encode(block_9_hash)
+            )
+        );
+    }
+
+    #[test]
+    fn test_validate_chainwork_monotonicity() {
+        assert_eq!(
+            monotonicity_test_transition().validate_chainwork_monotonicity(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_chainwork_monotonicity_rejects_zero_work_header() {
+        let mut transition = monotonicity_test_transition();
+        // a zero target contributes no work, so cumulative work stalls at block 5
+        transition.new_headers[4].0[72..76].copy_from_slice(&[0; 4]);
+
+        assert_eq!(
+            transition.validate_chainwork_monotonicity(),
+            Err(ChainworkMonotonicityError::ChainworkNotIncreasing { height: 5 })
+        );
+    }
+
+    #[test]
+    fn test_validate_chainwork_monotonicity_rejects_corrupt_leaf_chainwork() {
+        let mut transition = monotonicity_test_transition();
+        transition.parent.mmr_data.leaf.cumulative_chainwork = [0; 32];
+
+        assert_eq!(
+            transition.validate_chainwork_monotonicity(),
+            Err(ChainworkMonotonicityError::ImplausibleLeafChainwork {
+                height: 0,
+                cumulative_chainwork: [0; 32],
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_chainwork_monotonicity_rejects_heavier_retarget() {
+        let mut transition = monotonicity_test_transition();
+        transition
+            .parent_retarget
+            .mmr_data
+            .leaf
+            .cumulative_chainwork = [0xff; 32];
+
+        assert_eq!(
+            transition.validate_chainwork_monotonicity(),
+            Err(ChainworkMonotonicityError::RetargetNotAncestor {
+                retarget_height: 0,
+                parent_height: 0,
+            })
+        );
+    }
+
+    async fn genesis_extension_transition() -> ChainTransition {
+        let (genesis_client_index, genesis_header, genesis_leaf, client_mmr, mmr) =
+            create_from_genesis().await;
+        let genesis_proven_leaf = ProvenLeaf {
+            leaf: genesis_leaf,
+            proof: client_mmr_proof_to_circuit_mmr_proof(
+                &client_mmr
+                    .get_proof(genesis_client_index, None)
+                    .await
+                    .unwrap(),
+            ),
+        };
+        let genesis_block = VerifiedBlock {
+            header: genesis_header,
+            mmr_data: genesis_proven_leaf.clone(),
+        };
+
+        ChainTransition::new(
+            mmr.get_root(),
+            mmr.bag_peaks().unwrap(),
+            genesis_block.clone(),
+            genesis_block,
+            genesis_proven_leaf,
+            mmr.peaks,
+            vec![],
+            TEST_HEADERS[1..4]
+                .iter()
+                .map(|(_, header)| Header(*header))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_chain_transition_validate_consistency() {
+        let transition = genesis_extension_transition().await;
+        assert_eq!(transition.validate_consistency::<Keccak256Hasher>(), Ok(()));
+
+        let mut tampered = transition.clone();
+        tampered.current_mmr_root = [0xff; 32];
+        assert_eq!(
+            tampered.validate_consistency::<Keccak256Hasher>(),
+            Err(ChainTransitionError::MmrRootMismatch)
+        );
+
+        let mut tampered = transition;
+        tampered.parent.header.0[0] ^= 1;
+        assert_eq!(
+            tampered.validate_consistency::<Keccak256Hasher>(),
+            Err(ChainTransitionError::ParentLeafMismatch)
+        );
+    }
+
     // Create an MMR up to the bch fork block (block 478558), then commit to 10 BCH blocks, then dispose of the 10 BCH blocks, simultaneously commiting to 11 bitcoin blocks
     // Then validate the new MMR root and public inputs
     #[tokio::test]
@@ -1,9 +1,12 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fmt::Debug;
 
 use crypto_bigint::CheckedAdd;
+use crypto_bigint::CheckedMul;
 use crypto_bigint::Encoding;
 use crypto_bigint::U256;
+use hex_literal::hex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -23,6 +26,14 @@ impl Header {
     pub fn as_bytes(&self) -> &[u8; 80] {
         &self.0
     }
+
+    pub fn time(&self) -> u32 {
+        u32::from_le_bytes(
+            self.0[68..72]
+                .try_into()
+                .expect("conversion should never fail"),
+        )
+    }
 }
 
 impl Default for Header {
@@ -70,6 +81,266 @@ impl TryFrom<Vec<u8>> for Header {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderChainError {
+    EmptyChain,
+    BrokenLink { height: u32 },
+    InvalidWorkRequirement { height: u32 },
+    InvalidProofOfWork { height: u32 },
+    TipHashMismatch { expected: [u8; 32], found: [u8; 32] },
+    RetargetMismatch { parent_height: u32 },
+    UnlinkedHeaders { count: usize },
+    BannedBlock { index: usize, hash: [u8; 32] },
+    ParentLeafMismatch { height: u32 },
+    ParentHeaderRequired { height: u32 },
+    GenesisMismatch { expected: [u8; 32], found: [u8; 32] },
+    InvalidRetargetCheckpoint { height: u32 },
+    CumulativeWorkMismatch { height: u32 },
+}
+
+impl fmt::Display for HeaderChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderChainError::EmptyChain => write!(f, "Header chain must not be empty"),
+            HeaderChainError::BrokenLink { height } => {
+                write!(f, "Header chain link is not connected at height {}", height)
+            }
+            HeaderChainError::InvalidWorkRequirement { height } => {
+                write!(
+                    f,
+                    "Failed to validate work requirement at height {}",
+                    height
+                )
+            }
+            HeaderChainError::InvalidProofOfWork { height } => {
+                write!(f, "Header fails PoW check at height {}", height)
+            }
+            HeaderChainError::RetargetMismatch { parent_height } => {
+                write!(
+                    f,
+                    "Parent retarget header is not from the difficulty period of parent height {}",
+                    parent_height
+                )
+            }
+            HeaderChainError::UnlinkedHeaders { count } => {
+                write!(
+                    f,
+                    "{} headers do not form a single chain from the parent",
+                    count
+                )
+            }
+            HeaderChainError::ParentLeafMismatch { height } => {
+                write!(
+                    f,
+                    "Parent header does not match the leaf at height {}",
+                    height
+                )
+            }
+            HeaderChainError::ParentHeaderRequired { height } => {
+                write!(
+                    f,
+                    "Header at height {} starts a difficulty period, validating it requires the parent header",
+                    height
+                )
+            }
+            HeaderChainError::BannedBlock { index, hash } => {
+                write!(
+                    f,
+                    "Header chain contains banned block {} at index {}",
+                    hex::encode(hash),
+                    index
+                )
+            }
+            HeaderChainError::InvalidRetargetCheckpoint { height } => {
+                write!(
+                    f,
+                    "Checkpoint at height {} is not the retarget boundary following the previous checkpoint",
+                    height
+                )
+            }
+            HeaderChainError::CumulativeWorkMismatch { height } => {
+                write!(
+                    f,
+                    "Claimed cumulative work does not match the work of the period ending at height {}",
+                    height
+                )
+            }
+            HeaderChainError::GenesisMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Genesis header mismatch: expected block {}, found {}",
+                    hex::encode(expected),
+                    hex::encode(found)
+                )
+            }
+            HeaderChainError::TipHashMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Header chain tip hash mismatch: expected {}, found {}",
+                    hex::encode(expected),
+                    hex::encode(found)
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeaderChainError {}
+
+impl HeaderChainError {
+    /// Height of the header in the chain that failed validation, if the error is about one
+    pub fn failing_height(&self) -> Option<u32> {
+        match self {
+            HeaderChainError::BrokenLink { height }
+            | HeaderChainError::InvalidWorkRequirement { height }
+            | HeaderChainError::InvalidProofOfWork { height } => Some(*height),
+            _ => None,
+        }
+    }
+}
+
+/// A `HeaderChainError` with the block hashes (display byte order, as shown by block
+/// explorers) of the headers around the failing header, where available
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderChainErrorContext {
+    pub error: HeaderChainError,
+    pub previous_block_hash: Option<[u8; 32]>,
+    pub next_block_hash: Option<[u8; 32]>,
+}
+
+impl fmt::Display for HeaderChainErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(previous_block_hash) = self.previous_block_hash {
+            write!(f, ", previous block {}", hex::encode(previous_block_hash))?;
+        }
+        if let Some(next_block_hash) = self.next_block_hash {
+            write!(f, ", next block {}", hex::encode(next_block_hash))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HeaderChainErrorContext {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetargetError {
+    pub height: u32,
+    pub expected_bits: u32,
+    pub found_bits: u32,
+}
+
+impl fmt::Display for RetargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid difficulty at height {}: expected bits {:#010x}, found {:#010x}",
+            self.height, self.expected_bits, self.found_bits
+        )
+    }
+}
+
+impl std::error::Error for RetargetError {}
+
+// Validates the difficulty of `current_header`, the child of `previous_header` at
+// `previous_height`, against the retarget header of the previous header's difficulty period.
+// Returns the retarget header for `current_header`'s period: `current_header` itself if it
+// starts a new period, otherwise `retarget_header` unchanged.
+pub fn validate_retarget(
+    retarget_header: &Header,
+    previous_height: u32,
+    previous_header: &Header,
+    current_header: &Header,
+) -> Result<Header, RetargetError> {
+    bitcoin_core_rs::validate_next_work_required(
+        retarget_header.as_bytes(),
+        previous_height,
+        previous_header.as_bytes(),
+        current_header.as_bytes(),
+    )
+    .map(Header)
+    .map_err(|_| RetargetError {
+        height: previous_height + 1,
+        expected_bits: u32::from_le_bytes(bitcoin_core_rs::calculate_next_work_required(
+            retarget_header.as_bytes(),
+            previous_header.as_bytes(),
+        )),
+        found_bits: u32::from_le_bytes(
+            current_header.0[72..76]
+                .try_into()
+                .expect("conversion should never fail"),
+        ),
+    })
+}
+
+// How close to the 4x adjustment limit a retarget has to be to count as `near_clamp`
+const RETARGET_NEAR_CLAMP_FRACTION: f64 = 0.95;
+
+/// How much the difficulty moved at a retarget, for monitoring rather than validation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetargetAnalysis {
+    /// New difficulty over the previous period's difficulty, above 1 when mining got harder
+    pub difficulty_ratio: f64,
+    /// Whether the adjustment came within 5% of the 4x limit in either direction, which can
+    /// indicate timestamp manipulation or an unstable hashrate
+    pub near_clamp: bool,
+}
+
+fn target_to_f64(target: U256) -> f64 {
+    target
+        .to_be_bytes()
+        .iter()
+        .fold(0f64, |acc, byte| acc * 256.0 + *byte as f64)
+}
+
+// Compares the difficulty of two consecutive retarget headers
+pub fn analyze_retarget(
+    prev_retarget_header: &Header,
+    new_retarget_header: &Header,
+) -> RetargetAnalysis {
+    let target = |header: &Header| {
+        bitcoin_core_rs::bits_to_target(
+            &header.0[72..76]
+                .try_into()
+                .expect("conversion should never fail"),
+        )
+    };
+    let difficulty_ratio =
+        target_to_f64(target(prev_retarget_header)) / target_to_f64(target(new_retarget_header));
+    let clamp = 4.0 * RETARGET_NEAR_CLAMP_FRACTION;
+    RetargetAnalysis {
+        difficulty_ratio,
+        near_clamp: difficulty_ratio >= clamp || difficulty_ratio <= 1.0 / clamp,
+    }
+}
+
+/// Consensus parameters a header chain is validated against, mainnet's by default. Custom
+/// networks derived from bitcoin can supply their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainParams {
+    /// Easiest allowed target, headers with a target above it fail the PoW check
+    pub pow_limit: U256,
+    /// Blocks per difficulty period. The target timespan scales with it, keeping mainnet's
+    /// block spacing.
+    pub retarget_interval: u32,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            pow_limit: bitcoin_core_rs::POW_LIMIT,
+            retarget_interval: bitcoin_core_rs::DIFFICULTY_ADJUSTMENT_INTERVAL,
+        }
+    }
+}
+
+impl ChainParams {
+    fn target_timespan(&self) -> u32 {
+        self.retarget_interval
+            * (bitcoin_core_rs::TARGET_BLOCK_TIME / bitcoin_core_rs::DIFFICULTY_ADJUSTMENT_INTERVAL)
+    }
+}
+
 // parent_ variables are assumed to be valid in the context of the header chain
 // panics on any failures
 // TODO: No panics, return proper errors
@@ -79,47 +350,582 @@ pub fn validate_header_chain(
     parent_retarget_header: &Header,
     header_chain: &[Header],
 ) {
-    assert!(!header_chain.is_empty(), "Header chain must not be empty");
+    if let Err(e) = try_validate_header_chain(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        None,
+    ) {
+        panic!("{}", e);
+    }
+}
 
-    let mut retarget_header = *parent_retarget_header;
+// Same as `validate_header_chain`, but returns the first failure instead of panicking.
+// If `expected_tip_hash` is set (natural byte order, as in `BlockLeaf::natural_block_hash`),
+// a valid chain must also end in a header with that hash.
+pub fn try_validate_header_chain(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+    expected_tip_hash: Option<[u8; 32]>,
+) -> Result<(), HeaderChainError> {
+    try_validate_header_chain_with_params(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        expected_tip_hash,
+        &ChainParams::default(),
+    )
+}
 
-    for (i, pair) in std::iter::once(parent_header)
-        .chain(header_chain.iter())
-        .collect::<Vec<_>>()
-        .windows(2)
-        .enumerate()
+// Same as `try_validate_header_chain`, validating against `params` instead of mainnet's
+pub fn try_validate_header_chain_with_params(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+    expected_tip_hash: Option<[u8; 32]>,
+    params: &ChainParams,
+) -> Result<(), HeaderChainError> {
+    if let Some(e) = collect_header_chain_errors(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        params,
+        false,
+    )
+    .into_iter()
+    .next()
     {
-        let previous_header = pair[0];
+        return Err(e);
+    }
+
+    if let Some(expected) = expected_tip_hash {
+        let tip = header_chain.last().expect("Validated chain is not empty");
+        let found =
+            bitcoin_core_rs::get_block_hash(tip.as_bytes()).expect("Block hash calculation failed");
+        if found != expected {
+            return Err(HeaderChainError::TipHashMismatch { expected, found });
+        }
+    }
+
+    Ok(())
+}
+
+// Same as `try_validate_header_chain`, but a failure at a header in the chain also carries the
+// hashes of its neighbors (the parent header if the first header failed).
+pub fn try_validate_header_chain_with_context(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+    expected_tip_hash: Option<[u8; 32]>,
+) -> Result<(), HeaderChainErrorContext> {
+    try_validate_header_chain(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        expected_tip_hash,
+    )
+    .map_err(|error| {
+        let display_block_hash = |header: &Header| {
+            let mut block_hash = bitcoin_core_rs::get_block_hash(header.as_bytes())
+                .expect("Block hash calculation failed");
+            block_hash.reverse();
+            block_hash
+        };
+        let (previous_block_hash, next_block_hash) = match error.failing_height() {
+            Some(height) => {
+                let index = (height - parent_height - 1) as usize;
+                let previous = match index {
+                    0 => Some(parent_header),
+                    _ => header_chain.get(index - 1),
+                };
+                (
+                    previous.map(display_block_hash),
+                    header_chain.get(index + 1).map(display_block_hash),
+                )
+            }
+            None => (None, None),
+        };
+        HeaderChainErrorContext {
+            error,
+            previous_block_hash,
+            next_block_hash,
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitcoinNetwork {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl BitcoinNetwork {
+    /// Hash of the network's genesis block, natural byte order (as returned by
+    /// `bitcoin_core_rs::get_block_hash`)
+    pub fn genesis_block_hash(&self) -> [u8; 32] {
+        let mut block_hash = match self {
+            BitcoinNetwork::Mainnet => {
+                hex!("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")
+            }
+            BitcoinNetwork::Testnet => {
+                hex!("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+            }
+            BitcoinNetwork::Regtest => {
+                hex!("0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206")
+            }
+        };
+        block_hash.reverse();
+        block_hash
+    }
+}
+
+// Errors unless `genesis_header` is the genesis block of `network`. Errors carry hashes in
+// natural byte order.
+pub fn check_genesis_header(
+    genesis_header: &Header,
+    network: BitcoinNetwork,
+) -> Result<(), HeaderChainError> {
+    let expected = network.genesis_block_hash();
+    let found = bitcoin_core_rs::get_block_hash(genesis_header.as_bytes())
+        .expect("Block hash calculation failed");
+    if found != expected {
+        return Err(HeaderChainError::GenesisMismatch { expected, found });
+    }
+    Ok(())
+}
+
+// Same as `try_validate_header_chain` for a chain built directly on the genesis block, which is
+// otherwise trusted implicitly, but first checks `genesis_header` is `network`'s genesis block.
+pub fn try_validate_header_chain_from_genesis(
+    network: BitcoinNetwork,
+    genesis_header: &Header,
+    header_chain: &[Header],
+) -> Result<(), HeaderChainError> {
+    check_genesis_header(genesis_header, network)?;
+    try_validate_header_chain(0, genesis_header, genesis_header, header_chain, None)
+}
+
+// Rejects a chain containing any block in `banned_hashes` (natural byte order, as returned by
+// `bitcoin_core_rs::get_block_hash`), regardless of its work. A manual override so operators
+// can keep the light client off a known bad chain.
+pub fn check_banned_blocks(
+    header_chain: &[Header],
+    banned_hashes: &HashSet<[u8; 32]>,
+) -> Result<(), HeaderChainError> {
+    if banned_hashes.is_empty() {
+        return Ok(());
+    }
+    for (index, header) in header_chain.iter().enumerate() {
+        let hash = bitcoin_core_rs::get_block_hash(header.as_bytes())
+            .expect("Block hash calculation failed");
+        if banned_hashes.contains(&hash) {
+            return Err(HeaderChainError::BannedBlock { index, hash });
+        }
+    }
+    Ok(())
+}
+
+// Same as `try_validate_header_chain`, additionally rejecting chains containing a banned block
+pub fn try_validate_header_chain_with_banned_hashes(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+    banned_hashes: &HashSet<[u8; 32]>,
+) -> Result<(), HeaderChainError> {
+    check_banned_blocks(header_chain, banned_hashes)?;
+    try_validate_header_chain(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        None,
+    )
+}
+
+// Validates the whole chain, continuing past work requirement and PoW failures so every
+// invalid header is reported. A broken link still stops validation, nothing past it is
+// part of the chain. Returns an empty vec if the chain is valid.
+pub fn validate_header_chain_collect_errors(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+) -> Vec<HeaderChainError> {
+    collect_header_chain_errors(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        header_chain,
+        &ChainParams::default(),
+        true,
+    )
+}
+
+// The parent's retarget header is the first block of the parent's difficulty period
+// (height `parent_height - parent_height % retarget_interval`). Headers don't commit to their
+// height, so this checks what can be checked: a parent that starts a period is its own
+// retarget header, otherwise both share the period's difficulty bits.
+fn is_parent_retarget_consistent(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    retarget_interval: u32,
+) -> bool {
+    if bitcoin_core_rs::is_retarget_height(parent_height, retarget_interval) {
+        parent_retarget_header == parent_header
+    } else {
+        parent_retarget_header.0[72..76] == parent_header.0[72..76]
+    }
+}
+
+// A header's target must be within `pow_limit` and its hash must meet that target
+fn check_proof_of_work_with_limit(header: &Header, pow_limit: &U256) -> bool {
+    let target = bitcoin_core_rs::bits_to_target(
+        &header.0[72..76]
+            .try_into()
+            .expect("conversion should never fail"),
+    );
+    target <= *pow_limit
+        && bitcoin_core_rs::check_proof_of_work_with_target(header.as_bytes(), &target)
+}
+
+fn collect_header_chain_errors(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    header_chain: &[Header],
+    params: &ChainParams,
+    continue_on_error: bool,
+) -> Vec<HeaderChainError> {
+    if header_chain.is_empty() {
+        return vec![HeaderChainError::EmptyChain];
+    }
+
+    if !is_parent_retarget_consistent(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        params.retarget_interval,
+    ) {
+        return vec![HeaderChainError::RetargetMismatch { parent_height }];
+    }
+
+    let mut errors = Vec::new();
+    let mut retarget_header = *parent_retarget_header;
+    let mut previous_header = parent_header;
+
+    for (i, current_header) in header_chain.iter().enumerate() {
         let previous_height = parent_height + i as u32;
-        let current_header = pair[1];
+        let height = previous_height + 1;
 
-        assert!(
-            bitcoin_core_rs::check_header_connection(
-                current_header.as_bytes(),
-                previous_header.as_bytes(),
-            ),
-            "Header chain link is not connected"
-        );
+        if !bitcoin_core_rs::check_header_connection(
+            current_header.as_bytes(),
+            previous_header.as_bytes(),
+        ) {
+            errors.push(HeaderChainError::BrokenLink { height });
+            break;
+        }
 
-        let next_retarget = bitcoin_core_rs::validate_next_work_required(
-            &retarget_header.as_bytes(),
+        match bitcoin_core_rs::validate_next_work_required_with_params(
+            retarget_header.as_bytes(),
             previous_height,
-            &previous_header.as_bytes(),
-            &current_header.as_bytes(),
-        );
+            previous_header.as_bytes(),
+            current_header.as_bytes(),
+            &params.pow_limit,
+            params.retarget_interval,
+        ) {
+            Ok(next_retarget) => retarget_header = Header(next_retarget),
+            Err(_) => {
+                errors.push(HeaderChainError::InvalidWorkRequirement { height });
+                if !continue_on_error {
+                    break;
+                }
+                // keep advancing the retarget window as if the header were valid
+                if bitcoin_core_rs::is_retarget_height(height, params.retarget_interval) {
+                    retarget_header = *current_header;
+                }
+            }
+        }
 
-        assert!(
-            next_retarget.is_ok(),
-            "Failed to validate work requirement: {:?}",
-            next_retarget.err().unwrap()
-        );
+        if !check_proof_of_work_with_limit(current_header, &params.pow_limit) {
+            errors.push(HeaderChainError::InvalidProofOfWork { height });
+            if !continue_on_error {
+                break;
+            }
+        }
 
-        assert!(
-            bitcoin_core_rs::check_proof_of_work(&current_header.as_bytes()),
-            "Header fails PoW check"
+        previous_header = current_header;
+    }
+
+    errors
+}
+
+/// Trusted starting point for a header chain: the parent block, the retarget block of
+/// the parent's difficulty period, and the parent's cumulative chainwork.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeaderChainCheckpoint {
+    pub height: u32,
+    pub header: Header,
+    pub retarget_header: Header,
+    pub cumulative_work: U256,
+}
+
+impl HeaderChainCheckpoint {
+    // panics on any failures, see `validate_header_chain`
+    // Returns the cumulative chainwork for each new header and the final cumulative chainwork for the chain
+    pub fn validate_header_chain(&self, header_chain: &[Header]) -> (Vec<U256>, U256) {
+        validate_header_chain(
+            self.height,
+            &self.header,
+            &self.retarget_header,
+            header_chain,
         );
+        calculate_cumulative_work(self.cumulative_work, header_chain)
+    }
+}
+
+// Same as `try_validate_header_chain` for headers in any order: orders them by following
+// previous block hashes from the parent, then validates. Returns the ordered chain.
+pub fn validate_unordered_header_chain(
+    parent_height: u32,
+    parent_header: &Header,
+    parent_retarget_header: &Header,
+    headers: &[Header],
+) -> Result<Vec<Header>, HeaderChainError> {
+    let mut children: HashMap<[u8; 32], Header> = HashMap::with_capacity(headers.len());
+    for header in headers {
+        let previous_block_hash: [u8; 32] = header.0[4..36]
+            .try_into()
+            .expect("conversion should never fail");
+        // two headers sharing a parent can't both be in a single chain
+        if children.insert(previous_block_hash, *header).is_some() {
+            return Err(HeaderChainError::UnlinkedHeaders { count: 1 });
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(headers.len());
+    let mut tip = *parent_header;
+    loop {
+        let tip_hash =
+            bitcoin_core_rs::get_block_hash(tip.as_bytes()).expect("Block hash calculation failed");
+        match children.remove(&tip_hash) {
+            Some(child) => {
+                ordered.push(child);
+                tip = child;
+            }
+            None => break,
+        }
+    }
+    if !children.is_empty() {
+        return Err(HeaderChainError::UnlinkedHeaders {
+            count: children.len(),
+        });
+    }
+
+    try_validate_header_chain(
+        parent_height,
+        parent_header,
+        parent_retarget_header,
+        &ordered,
+        None,
+    )?;
+    Ok(ordered)
+}
+
+// Returns the index of the first block that differs between the two chains, or the length of
+// the shorter chain if one is a prefix of the other. Returns None if the chains are identical.
+// Headers are compared byte for byte, which is equivalent to comparing their block hashes, and
+// block leaves commit to their block hash, so either works.
+pub fn find_divergence<T: PartialEq>(chain_a: &[T], chain_b: &[T]) -> Option<usize> {
+    chain_a
+        .iter()
+        .zip(chain_b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (chain_a.len() != chain_b.len()).then_some(chain_a.len().min(chain_b.len())))
+}
+
+// Number of `old_chain` headers replaced when reorging to `new_chain`, both starting at the same
+// height. Zero if `new_chain` only extends (or equals) `old_chain`.
+pub fn reorg_depth(old_chain: &[Header], new_chain: &[Header]) -> usize {
+    find_divergence(old_chain, new_chain).map_or(0, |divergence| old_chain.len() - divergence)
+}
+
+// Counts observed reorgs by depth (depth -> count), each reorg given as the chain that was
+// replaced and the chain that replaced it. For sizing fork depth and update limits from
+// historical data.
+pub fn reorg_depth_distribution<'a>(
+    reorgs: impl IntoIterator<Item = (&'a [Header], &'a [Header])>,
+) -> BTreeMap<usize, usize> {
+    let mut distribution = BTreeMap::new();
+    for (old_chain, new_chain) in reorgs {
+        *distribution
+            .entry(reorg_depth(old_chain, new_chain))
+            .or_insert(0) += 1;
+    }
+    distribution
+}
+
+/// Persistable state of a `HeaderChainValidator`, see `HeaderChainValidator::snapshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorSnapshot {
+    pub height: u32,
+    pub tip: Header,
+    pub retarget_header: Header,
+}
+
+/// Validates a header chain incrementally, one header at a time, from a trusted tip.
+/// The difficulty target is constant within a retarget window, so the decoded target of
+/// the last header is cached and reused for PoW checks until the bits change.
+#[derive(Debug, Clone)]
+pub struct HeaderChainValidator {
+    height: u32,
+    tip: Header,
+    retarget_header: Header,
+    params: ChainParams,
+    cached_target: Option<([u8; 4], U256)>,
+    target_decodes: usize,
+}
+
+impl HeaderChainValidator {
+    // tip and retarget_header are assumed to be valid, see `validate_header_chain`
+    pub fn new(height: u32, tip: Header, retarget_header: Header) -> Self {
+        Self {
+            height,
+            tip,
+            retarget_header,
+            params: ChainParams::default(),
+            cached_target: None,
+            target_decodes: 0,
+        }
+    }
+
+    /// Validates against `pow_limit` (the easiest allowed target) instead of mainnet's, for
+    /// custom networks. Headers with a target above the limit fail the PoW check.
+    pub fn with_pow_limit(mut self, pow_limit: U256) -> Self {
+        self.params.pow_limit = pow_limit;
+        self
+    }
+
+    /// Validates difficulty periods of `retarget_interval` blocks instead of mainnet's 2016,
+    /// for custom networks. The target timespan scales with the interval.
+    pub fn with_retarget_interval(mut self, retarget_interval: u32) -> Self {
+        self.params.retarget_interval = retarget_interval;
+        self
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tip(&self) -> &Header {
+        &self.tip
+    }
+
+    pub fn retarget_header(&self) -> &Header {
+        &self.retarget_header
+    }
+
+    /// Captures the state needed to resume validation after a restart. The target cache is
+    /// not included, it is rebuilt on the first header validated after a restore.
+    pub fn snapshot(&self) -> ValidatorSnapshot {
+        ValidatorSnapshot {
+            height: self.height,
+            tip: self.tip,
+            retarget_header: self.retarget_header,
+        }
+    }
+
+    /// Resumes from a snapshot, rejecting one whose retarget header can't belong to the
+    /// difficulty period of its tip (see `is_parent_retarget_consistent`)
+    pub fn restore(snapshot: ValidatorSnapshot) -> Result<Self, HeaderChainError> {
+        Self::restore_with_params(snapshot, ChainParams::default())
+    }
+
+    /// Same as `restore`, for a validator of a network with custom `params`
+    pub fn restore_with_params(
+        snapshot: ValidatorSnapshot,
+        params: ChainParams,
+    ) -> Result<Self, HeaderChainError> {
+        if !is_parent_retarget_consistent(
+            snapshot.height,
+            &snapshot.tip,
+            &snapshot.retarget_header,
+            params.retarget_interval,
+        ) {
+            return Err(HeaderChainError::RetargetMismatch {
+                parent_height: snapshot.height,
+            });
+        }
+        let mut validator = Self::new(snapshot.height, snapshot.tip, snapshot.retarget_header);
+        validator.params = params;
+        Ok(validator)
+    }
+
+    /// Validates `header` as the child of the current tip and advances to it.
+    /// On failure the validator is left unchanged.
+    pub fn validate_next(&mut self, header: &Header) -> Result<(), HeaderChainError> {
+        let height = self.height + 1;
+
+        if !bitcoin_core_rs::check_header_connection(header.as_bytes(), self.tip.as_bytes()) {
+            return Err(HeaderChainError::BrokenLink { height });
+        }
 
-        retarget_header = Header(next_retarget.unwrap());
+        let next_retarget = bitcoin_core_rs::validate_next_work_required_with_params(
+            self.retarget_header.as_bytes(),
+            self.height,
+            self.tip.as_bytes(),
+            header.as_bytes(),
+            &self.params.pow_limit,
+            self.params.retarget_interval,
+        )
+        .map_err(|_| HeaderChainError::InvalidWorkRequirement { height })?;
+
+        let target = self.target(header);
+        if target > self.params.pow_limit
+            || !bitcoin_core_rs::check_proof_of_work_with_target(header.as_bytes(), &target)
+        {
+            return Err(HeaderChainError::InvalidProofOfWork { height });
+        }
+
+        self.height = height;
+        self.tip = *header;
+        self.retarget_header = Header(next_retarget);
+        Ok(())
+    }
+
+    /// Validates every header in order, stopping at the first failure.
+    pub fn validate(&mut self, header_chain: &[Header]) -> Result<(), HeaderChainError> {
+        if header_chain.is_empty() {
+            return Err(HeaderChainError::EmptyChain);
+        }
+        header_chain
+            .iter()
+            .try_for_each(|header| self.validate_next(header))
+    }
+
+    fn target(&mut self, header: &Header) -> U256 {
+        let bits: [u8; 4] = header.0[72..76]
+            .try_into()
+            .expect("conversion should never fail");
+        match self.cached_target {
+            Some((cached_bits, target)) if cached_bits == bits => target,
+            _ => {
+                let target = bitcoin_core_rs::bits_to_target(&bits);
+                self.target_decodes += 1;
+                self.cached_target = Some((bits, target));
+                target
+            }
+        }
     }
 }
 
@@ -148,6 +954,117 @@ pub fn calculate_cumulative_work(
     (all_works, final_work)
 }
 
+// Same as `calculate_cumulative_work`, pairing each header's timestamp with its cumulative
+// chainwork so work accrual can be compared against elapsed time
+pub fn calculate_cumulative_work_with_timestamps(
+    parent_cumulative_work: U256,
+    header_chain: &[Header],
+) -> Vec<(u32, U256)> {
+    let (works, _) = calculate_cumulative_work(parent_cumulative_work, header_chain);
+    header_chain.iter().map(Header::time).zip(works).collect()
+}
+
+/// A difficulty retarget boundary header and the chain's cumulative work through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetargetCheckpoint {
+    pub height: u32,
+    pub header: Header,
+    pub cumulative_work: U256,
+}
+
+// Bits `header`'s difficulty period would retarget to if it lasted `timespan` seconds
+fn retarget_bits_for_timespan(header: &Header, timespan: u32, params: &ChainParams) -> [u8; 4] {
+    let mut retarget_header = *header.as_bytes();
+    retarget_header[68..72].copy_from_slice(&0u32.to_le_bytes());
+    let mut previous_header = *header.as_bytes();
+    previous_header[68..72].copy_from_slice(&timespan.to_le_bytes());
+    bitcoin_core_rs::calculate_next_work_required_with_params(
+        &retarget_header,
+        &previous_header,
+        &params.pow_limit,
+        params.retarget_interval,
+    )
+}
+
+// Validates a sparse chain of consecutive retarget boundary headers without the headers in
+// between: every checkpoint meets its own proof of work, each difficulty transition is within
+// the 4x adjustment bounds, and the claimed cumulative works increase by exactly the work of
+// the period between checkpoints (every header of a mainnet period shares its boundary's bits).
+// The intermediate headers' timestamps are unknown, so a transition is only checked against
+// the bounds rather than recomputed exactly.
+pub fn validate_retarget_checkpoints(
+    checkpoints: &[RetargetCheckpoint],
+) -> Result<(), HeaderChainError> {
+    validate_retarget_checkpoints_with_params(checkpoints, &ChainParams::default())
+}
+
+// Same as `validate_retarget_checkpoints`, validating against `params` instead of mainnet's
+pub fn validate_retarget_checkpoints_with_params(
+    checkpoints: &[RetargetCheckpoint],
+    params: &ChainParams,
+) -> Result<(), HeaderChainError> {
+    let first = checkpoints.first().ok_or(HeaderChainError::EmptyChain)?;
+    if !bitcoin_core_rs::is_retarget_height(first.height, params.retarget_interval) {
+        return Err(HeaderChainError::InvalidRetargetCheckpoint {
+            height: first.height,
+        });
+    }
+    let check_proof_of_work = |checkpoint: &RetargetCheckpoint| {
+        if !check_proof_of_work_with_limit(&checkpoint.header, &params.pow_limit) {
+            return Err(HeaderChainError::InvalidProofOfWork {
+                height: checkpoint.height,
+            });
+        }
+        Ok(())
+    };
+    check_proof_of_work(first)?;
+
+    for (previous, current) in checkpoints.iter().zip(checkpoints.iter().skip(1)) {
+        let height = current.height;
+        if Some(height) != previous.height.checked_add(params.retarget_interval) {
+            return Err(HeaderChainError::InvalidRetargetCheckpoint { height });
+        }
+
+        // timespans are clamped to [1/4, 4] target timespans, bounding the transition
+        let current_target = bitcoin_core_rs::bits_to_target(
+            &current.header.0[72..76]
+                .try_into()
+                .expect("conversion should never fail"),
+        );
+        let min_target = bitcoin_core_rs::bits_to_target(&retarget_bits_for_timespan(
+            &previous.header,
+            params.target_timespan() / 4,
+            params,
+        ));
+        let max_target = bitcoin_core_rs::bits_to_target(&retarget_bits_for_timespan(
+            &previous.header,
+            params.target_timespan() * 4,
+            params,
+        ));
+        if current_target < min_target || current_target > max_target {
+            return Err(HeaderChainError::InvalidWorkRequirement { height });
+        }
+        check_proof_of_work(current)?;
+
+        let block_proof = |header: &Header| {
+            U256::from_le_bytes(
+                bitcoin_core_rs::get_block_proof(header.as_bytes())
+                    .expect("Header proof calculation failed"),
+            )
+        };
+        let expected_work: Option<U256> = block_proof(&previous.header)
+            .checked_mul(&U256::from(params.retarget_interval - 1))
+            .and_then(|work| work.checked_add(&block_proof(&current.header)))
+            .and_then(|work| work.checked_add(&previous.cumulative_work))
+            .into();
+        if expected_work != Some(current.cumulative_work) {
+            return Err(HeaderChainError::CumulativeWorkMismatch { height });
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod tests {
     /*
@@ -164,6 +1081,57 @@ pub mod tests {
 
     use test_data_utils::{EXHAUSTIVE_TEST_HEADERS, TEST_HEADERS};
 
+    #[test]
+    fn test_check_genesis_header() {
+        let mainnet_genesis = Header(TEST_HEADERS[0].1);
+        let testnet_genesis = Header(hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae18"));
+        let regtest_genesis = Header(hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f2002000000"));
+
+        assert_eq!(
+            check_genesis_header(&mainnet_genesis, BitcoinNetwork::Mainnet),
+            Ok(())
+        );
+        assert_eq!(
+            check_genesis_header(&testnet_genesis, BitcoinNetwork::Testnet),
+            Ok(())
+        );
+        assert_eq!(
+            check_genesis_header(&regtest_genesis, BitcoinNetwork::Regtest),
+            Ok(())
+        );
+
+        assert_eq!(
+            check_genesis_header(&mainnet_genesis, BitcoinNetwork::Regtest),
+            Err(HeaderChainError::GenesisMismatch {
+                expected: BitcoinNetwork::Regtest.genesis_block_hash(),
+                found: BitcoinNetwork::Mainnet.genesis_block_hash(),
+            })
+        );
+        assert!(check_genesis_header(&regtest_genesis, BitcoinNetwork::Testnet).is_err());
+
+        let header_chain: Vec<Header> = TEST_HEADERS[1..=10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        assert_eq!(
+            try_validate_header_chain_from_genesis(
+                BitcoinNetwork::Mainnet,
+                &mainnet_genesis,
+                &header_chain
+            ),
+            Ok(())
+        );
+        // the first header isn't the genesis block at all
+        assert!(matches!(
+            try_validate_header_chain_from_genesis(
+                BitcoinNetwork::Mainnet,
+                &header_chain[0],
+                &header_chain[1..]
+            ),
+            Err(HeaderChainError::GenesisMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_validate_header_chain_genesis_step() {
         let genesis_header = &Header(TEST_HEADERS[0].1);
@@ -173,6 +1141,48 @@ pub mod tests {
         validate_header_chain(0, genesis_header, genesis_header, &header_chain);
     }
 
+    #[test]
+    fn test_chained_single_header_validations_carry_retarget_header() {
+        let header = |height: usize| Header(TEST_HEADERS[height].1);
+
+        // genesis -> 1: block 1 is mid-period, so the retarget header stays genesis
+        let mut validator = HeaderChainValidator::new(0, header(0), header(0));
+        assert_eq!(validator.validate(&[header(1)]), Ok(()));
+        assert_eq!(*validator.retarget_header(), header(0));
+        assert_eq!(
+            try_validate_header_chain(
+                1,
+                &header(1),
+                validator.retarget_header(),
+                &[header(2)],
+                None
+            ),
+            Ok(())
+        );
+
+        // 2015 -> 2016: block 2016 starts a new period and becomes the retarget header
+        let mut validator = HeaderChainValidator::new(2015, header(2015), header(0));
+        assert_eq!(validator.validate(&[header(2016)]), Ok(()));
+        assert_eq!(*validator.retarget_header(), header(2016));
+        assert_eq!(
+            try_validate_header_chain(
+                2016,
+                &header(2016),
+                validator.retarget_header(),
+                &[header(2017)],
+                None
+            ),
+            Ok(())
+        );
+        // the stale retarget header is rejected for the next call
+        assert_eq!(
+            try_validate_header_chain(2016, &header(2016), &header(0), &[header(2017)], None),
+            Err(HeaderChainError::RetargetMismatch {
+                parent_height: 2016
+            })
+        );
+    }
+
     #[test]
     fn test_validate_header_chain_first_10_blocks() {
         let genesis_header = &Header(TEST_HEADERS[0].1);
@@ -302,13 +1312,83 @@ pub mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Failed to validate work requirement")]
-    fn test_validate_header_chain_invalid_difficulty_update() {
+    fn test_validate_header_chain_error_includes_neighbor_hashes() {
         let genesis_header = &Header(TEST_HEADERS[0].1);
-        let mut chain = TEST_HEADERS[1..2017]
+        let mut header_chain: Vec<Header> = TEST_HEADERS[1..=10]
             .iter()
             .map(|(_, header)| Header(*header))
-            .collect::<Vec<_>>();
+            .collect();
+        // break the proof of work of block 5
+        header_chain[4].0[76..80].copy_from_slice(&[0; 4]);
+
+        let error = try_validate_header_chain_with_context(
+            0,
+            genesis_header,
+            genesis_header,
+            &header_chain,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.error,
+            HeaderChainError::InvalidProofOfWork { height: 5 }
+        );
+
+        let message = error.to_string();
+        assert!(
+            message.contains("000000004ebadb55ee9096c9a2f8880e09da59c0d68b1c228da88e48844a1485")
+        );
+        assert!(
+            message.contains("000000003031a0e73735690c5a1ff2a4be82553b2a12b776fbd3a215dc8f778d")
+        );
+
+        // the tip has no next header, the parent stands in for the previous header of the first
+        let mut tip = Header(TEST_HEADERS[1].1);
+        tip.0[76..80].copy_from_slice(&[0; 4]);
+        let error =
+            try_validate_header_chain_with_context(0, genesis_header, genesis_header, &[tip], None)
+                .unwrap_err();
+        assert_eq!(
+            error.previous_block_hash.map(hex::encode).as_deref(),
+            Some("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")
+        );
+        assert_eq!(error.next_block_hash, None);
+    }
+
+    #[test]
+    fn test_validate_header_chain_rejects_broken_parent_link() {
+        // the chain 101..=110 is internally linked, only its connection to the parent is wrong
+        let header_chain: Vec<Header> = TEST_HEADERS[101..=110]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let wrong_parent = &Header(TEST_HEADERS[99].1);
+
+        assert_eq!(
+            try_validate_header_chain(100, wrong_parent, genesis_header, &header_chain, None),
+            Err(HeaderChainError::BrokenLink { height: 101 })
+        );
+        assert_eq!(
+            validate_header_chain_collect_errors(100, wrong_parent, genesis_header, &header_chain),
+            vec![HeaderChainError::BrokenLink { height: 101 }]
+        );
+
+        let parent = &Header(TEST_HEADERS[100].1);
+        assert_eq!(
+            try_validate_header_chain(100, parent, genesis_header, &header_chain, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to validate work requirement")]
+    fn test_validate_header_chain_invalid_difficulty_update() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let mut chain = TEST_HEADERS[1..2017]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect::<Vec<_>>();
 
         // Create a new modified header
         let mut modified_bytes = *chain[2015].as_bytes();
@@ -318,6 +1398,244 @@ pub mod tests {
         validate_header_chain(0, genesis_header, genesis_header, &chain);
     }
 
+    #[test]
+    fn test_validate_unordered_header_chain() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..50]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let mut shuffled = header_chain.clone();
+        shuffled.reverse();
+        shuffled.swap(3, 40);
+        shuffled.swap(10, 20);
+
+        assert_eq!(
+            validate_unordered_header_chain(0, genesis_header, genesis_header, &shuffled),
+            Ok(header_chain)
+        );
+    }
+
+    #[test]
+    fn test_validate_unordered_header_chain_rejects_orphan() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        // block 60 doesn't connect to anything else in the batch
+        let mut headers: Vec<Header> = TEST_HEADERS[1..50]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        headers.insert(25, Header(TEST_HEADERS[60].1));
+
+        assert_eq!(
+            validate_unordered_header_chain(0, genesis_header, genesis_header, &headers),
+            Err(HeaderChainError::UnlinkedHeaders { count: 1 })
+        );
+
+        // an empty batch is still an empty chain
+        assert_eq!(
+            validate_unordered_header_chain(0, genesis_header, genesis_header, &[]),
+            Err(HeaderChainError::EmptyChain)
+        );
+    }
+
+    #[test]
+    fn test_validate_retarget_checkpoints() {
+        const LAST_CHECKPOINT: usize = 40 * 2016;
+        let headers: Vec<Header> = EXHAUSTIVE_TEST_HEADERS[..=LAST_CHECKPOINT]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let genesis_work =
+            U256::from_le_bytes(bitcoin_core_rs::get_block_proof(headers[0].as_bytes()).unwrap());
+        let (works, _) = calculate_cumulative_work(genesis_work, &headers[1..]);
+        let checkpoints: Vec<RetargetCheckpoint> = (0..=LAST_CHECKPOINT)
+            .step_by(2016)
+            .map(|height| RetargetCheckpoint {
+                height: height as u32,
+                header: headers[height],
+                cumulative_work: if height == 0 {
+                    genesis_work
+                } else {
+                    works[height - 1]
+                },
+            })
+            .collect();
+        // spans the first real difficulty changes
+        assert_ne!(
+            checkpoints[16].header.0[72..76],
+            checkpoints[0].header.0[72..76]
+        );
+        assert_eq!(validate_retarget_checkpoints(&checkpoints), Ok(()));
+
+        // overclaimed work
+        let mut tampered = checkpoints.clone();
+        tampered[20].cumulative_work = tampered[20].cumulative_work.wrapping_add(&U256::ONE);
+        assert_eq!(
+            validate_retarget_checkpoints(&tampered),
+            Err(HeaderChainError::CumulativeWorkMismatch { height: 20 * 2016 })
+        );
+
+        // difficulty jumping 256x in a single period
+        let mut tampered = checkpoints.clone();
+        tampered[30].header.0[75] -= 1;
+        assert_eq!(
+            validate_retarget_checkpoints(&tampered),
+            Err(HeaderChainError::InvalidWorkRequirement { height: 30 * 2016 })
+        );
+
+        // a skipped boundary
+        let mut tampered = checkpoints.clone();
+        tampered.remove(10);
+        assert_eq!(
+            validate_retarget_checkpoints(&tampered),
+            Err(HeaderChainError::InvalidRetargetCheckpoint { height: 11 * 2016 })
+        );
+
+        // a boundary whose header doesn't meet its own difficulty
+        let mut tampered = checkpoints.clone();
+        tampered[5].header.0[76..80].copy_from_slice(&[0; 4]);
+        assert_eq!(
+            validate_retarget_checkpoints(&tampered),
+            Err(HeaderChainError::InvalidProofOfWork { height: 5 * 2016 })
+        );
+    }
+
+    #[test]
+    fn test_analyze_retarget() {
+        let header = |height: usize| Header(EXHAUSTIVE_TEST_HEADERS[height].1);
+
+        // 0x1d00ffff -> 0x1d00d86a, the first difficulty increase
+        let analysis = analyze_retarget(&header(30240), &header(32256));
+        assert!((analysis.difficulty_ratio - 65535.0 / 55402.0).abs() < 1e-9);
+        assert!(!analysis.near_clamp);
+
+        // 0x1d008cc3 -> 0x1c654657
+        let analysis = analyze_retarget(&header(38304), &header(40320));
+        assert!((analysis.difficulty_ratio - 1.389899238).abs() < 1e-9);
+        assert!(!analysis.near_clamp);
+
+        let analysis = analyze_retarget(&header(2016), &header(4032));
+        assert_eq!(analysis.difficulty_ratio, 1.0);
+
+        // a quartered target is the most a single retarget can raise difficulty
+        let mut clamped = header(32256);
+        clamped.0[72..76].copy_from_slice(&0x1c3fffc0u32.to_le_bytes());
+        let analysis = analyze_retarget(&header(30240), &clamped);
+        assert_eq!(analysis.difficulty_ratio, 4.0);
+        assert!(analysis.near_clamp);
+        let analysis = analyze_retarget(&clamped, &header(30240));
+        assert_eq!(analysis.difficulty_ratio, 0.25);
+        assert!(analysis.near_clamp);
+    }
+
+    #[test]
+    fn test_validate_retarget() {
+        let header = |height: usize| Header(EXHAUSTIVE_TEST_HEADERS[height].1);
+
+        // first real difficulty change, at 32256
+        assert_eq!(
+            validate_retarget(&header(30240), 32255, &header(32255), &header(32256)),
+            Ok(header(32256))
+        );
+        // inside a period the retarget header carries over
+        assert_eq!(
+            validate_retarget(&header(32256), 32256, &header(32256), &header(32257)),
+            Ok(header(32256))
+        );
+
+        // keeping the old difficulty across the boundary is rejected
+        let mut stale_difficulty = header(32256);
+        stale_difficulty.0[72..76].copy_from_slice(&header(32255).0[72..76]);
+        assert_eq!(
+            validate_retarget(&header(30240), 32255, &header(32255), &stale_difficulty),
+            Err(RetargetError {
+                height: 32256,
+                expected_bits: 0x1d00d86a,
+                found_bits: 0x1d00ffff,
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_divergence() {
+        let chain: Vec<Header> = TEST_HEADERS[..10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert_eq!(find_divergence(&chain, &chain), None);
+        assert_eq!(find_divergence(&[], &[]), None);
+
+        let mut forked_chain = chain.clone();
+        forked_chain[6].0[76..80].copy_from_slice(&[0; 4]);
+        assert_eq!(find_divergence(&chain, &forked_chain), Some(6));
+        assert_eq!(find_divergence(&forked_chain, &chain), Some(6));
+
+        assert_eq!(find_divergence(&chain, &chain[..4]), Some(4));
+        assert_eq!(find_divergence(&chain[..4], &chain), Some(4));
+        assert_eq!(find_divergence(&chain, &[]), Some(0));
+    }
+
+    #[test]
+    fn test_reorg_depth_distribution() {
+        let chain: Vec<Header> = TEST_HEADERS[..10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        // a competing chain replacing every header from `height` on, one block longer
+        let fork_at = |height: usize| {
+            let mut fork = chain.clone();
+            fork.push(Header(TEST_HEADERS[10].1));
+            for header in &mut fork[height..] {
+                header.0[76..80].copy_from_slice(&[0xff; 4]);
+            }
+            fork
+        };
+        let (fork_8, fork_9, fork_3) = (fork_at(8), fork_at(9), fork_at(3));
+        let extension: Vec<Header> = TEST_HEADERS[..11]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert_eq!(reorg_depth(&chain, &fork_8), 2);
+        assert_eq!(reorg_depth(&chain, &extension), 0);
+
+        let distribution = reorg_depth_distribution([
+            (chain.as_slice(), fork_8.as_slice()),
+            (chain.as_slice(), fork_9.as_slice()),
+            (chain.as_slice(), extension.as_slice()),
+            (chain.as_slice(), fork_8.as_slice()),
+            (chain.as_slice(), fork_3.as_slice()),
+        ]);
+        assert_eq!(
+            distribution,
+            BTreeMap::from([(0, 1), (1, 1), (2, 2), (7, 1)])
+        );
+    }
+
+    #[test]
+    fn test_calculate_cumulative_work_with_timestamps() {
+        let header_chain: Vec<Header> = TEST_HEADERS[..5]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let (works, _) = calculate_cumulative_work(U256::ZERO, &header_chain);
+
+        let timestamped_works =
+            calculate_cumulative_work_with_timestamps(U256::ZERO, &header_chain);
+
+        assert_eq!(timestamped_works.len(), header_chain.len());
+        // genesis block timestamp
+        assert_eq!(timestamped_works[0].0, 1231006505);
+        for ((time, work), (header, expected_work)) in timestamped_works
+            .iter()
+            .zip(header_chain.iter().zip(works.iter()))
+        {
+            assert_eq!(*time, header.time());
+            assert_eq!(work, expected_work);
+        }
+    }
+
     #[test]
     #[should_panic]
     fn test_calculate_cumulative_work_overflow() {
@@ -349,4 +1667,543 @@ pub mod tests {
 
         validate_header_chain(0, genesis_header, genesis_header, &header_chain);
     }
+
+    fn relink(header: &mut Header, previous: &Header) {
+        let previous_hash = bitcoin_core_rs::get_block_hash(previous.as_bytes()).unwrap();
+        header.0[4..36].copy_from_slice(&previous_hash);
+    }
+
+    #[test]
+    fn test_validate_header_chain_collect_errors_valid_chain() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..100]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert!(validate_header_chain_collect_errors(
+            0,
+            genesis_header,
+            genesis_header,
+            &header_chain
+        )
+        .is_empty());
+        assert_eq!(
+            try_validate_header_chain(0, genesis_header, genesis_header, &header_chain, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_header_chain_validator_cached_target_matches_uncached() {
+        let genesis_header = Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        let mut validator = HeaderChainValidator::new(0, genesis_header, genesis_header);
+        assert_eq!(validator.validate(&header_chain), Ok(()));
+        assert_eq!(
+            try_validate_header_chain(0, &genesis_header, &genesis_header, &header_chain, None),
+            Ok(())
+        );
+        assert_eq!(validator.height(), header_chain.len() as u32);
+        assert_eq!(validator.tip(), header_chain.last().unwrap());
+
+        // one decode per distinct run of bits instead of one per header
+        let bit_changes = TEST_HEADERS
+            .windows(2)
+            .filter(|pair| pair[0].1[72..76] != pair[1].1[72..76])
+            .count();
+        assert_eq!(validator.target_decodes, bit_changes + 1);
+        assert!(validator.target_decodes < header_chain.len() / 1000);
+
+        // failures match the uncached path too
+        let mut invalid_chain = header_chain.clone();
+        invalid_chain[4500].0[76..80].copy_from_slice(&[0; 4]);
+        for i in 4501..invalid_chain.len() {
+            let previous = invalid_chain[i - 1];
+            relink(&mut invalid_chain[i], &previous);
+        }
+        let mut validator = HeaderChainValidator::new(0, genesis_header, genesis_header);
+        assert_eq!(
+            validator.validate(&invalid_chain),
+            try_validate_header_chain(0, &genesis_header, &genesis_header, &invalid_chain, None)
+        );
+        assert_eq!(validator.height(), 4500);
+    }
+
+    #[test]
+    fn test_header_chain_validator_snapshot_restore() {
+        let genesis_header = Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..=5000]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        let mut uninterrupted = HeaderChainValidator::new(0, genesis_header, genesis_header);
+        assert_eq!(uninterrupted.validate(&header_chain), Ok(()));
+
+        // stop partway through a difficulty period, persist, and resume
+        let mut validator = HeaderChainValidator::new(0, genesis_header, genesis_header);
+        assert_eq!(validator.validate(&header_chain[..2500]), Ok(()));
+        let persisted = serde_json::to_vec(&validator.snapshot()).unwrap();
+        let snapshot: ValidatorSnapshot = serde_json::from_slice(&persisted).unwrap();
+        let mut restored = HeaderChainValidator::restore(snapshot).unwrap();
+        assert_eq!(restored.validate(&header_chain[2500..]), Ok(()));
+
+        assert_eq!(restored.snapshot(), uninterrupted.snapshot());
+        assert_eq!(restored.height(), 5000);
+    }
+
+    #[test]
+    fn test_header_chain_validator_restore_rejects_inconsistent_snapshot() {
+        // block 4032 starts a difficulty period, so it must be its own retarget header
+        let snapshot = ValidatorSnapshot {
+            height: 4032,
+            tip: Header(TEST_HEADERS[4032].1),
+            retarget_header: Header(TEST_HEADERS[2016].1),
+        };
+
+        assert_eq!(
+            HeaderChainValidator::restore(snapshot).unwrap_err(),
+            HeaderChainError::RetargetMismatch {
+                parent_height: 4032
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_header_chain_from_mid_chain_parent() {
+        // bootstrap from a trusted header at 5000, whose retarget block is 4032, and cross
+        // the difficulty adjustment at 6048
+        let parent_height = 5000;
+        let parent_header = Header(TEST_HEADERS[parent_height as usize].1);
+        let retarget_height = bitcoin_core_rs::get_retarget_height(parent_height);
+        assert_eq!(retarget_height, 4032);
+        let retarget_header = Header(TEST_HEADERS[retarget_height as usize].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[parent_height as usize + 1..=6100]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        validate_header_chain(
+            parent_height,
+            &parent_header,
+            &retarget_header,
+            &header_chain,
+        );
+        assert_eq!(
+            try_validate_header_chain(
+                parent_height,
+                &parent_header,
+                &retarget_header,
+                &header_chain,
+                None
+            ),
+            Ok(())
+        );
+
+        let mut validator =
+            HeaderChainValidator::new(parent_height, parent_header, retarget_header);
+        assert_eq!(validator.validate(&header_chain), Ok(()));
+        assert_eq!(validator.height(), 6100);
+        assert_eq!(validator.retarget_header(), &Header(TEST_HEADERS[6048].1));
+    }
+
+    #[test]
+    fn test_validate_header_chain_rejects_inconsistent_parent_retarget() {
+        let header_chain: Vec<Header> = TEST_HEADERS[4033..4040]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let parent_header = Header(TEST_HEADERS[4032].1);
+
+        // 4032 starts a difficulty period, so it is its own retarget header, not 2016
+        assert_eq!(
+            try_validate_header_chain(4032, &parent_header, &parent_header, &header_chain, None),
+            Ok(())
+        );
+        assert_eq!(
+            try_validate_header_chain(
+                4032,
+                &parent_header,
+                &Header(TEST_HEADERS[2016].1),
+                &header_chain,
+                None
+            ),
+            Err(HeaderChainError::RetargetMismatch {
+                parent_height: 4032
+            })
+        );
+
+        // a retarget header from a period with different difficulty bits
+        let header_chain: Vec<Header> = TEST_HEADERS[5001..5010]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let mut retarget_header = Header(TEST_HEADERS[4032].1);
+        retarget_header.0[72..76].copy_from_slice(&[0xff, 0xff, 0x00, 0x1c]);
+        assert_eq!(
+            validate_header_chain_collect_errors(
+                5000,
+                &Header(TEST_HEADERS[5000].1),
+                &retarget_header,
+                &header_chain
+            ),
+            vec![HeaderChainError::RetargetMismatch {
+                parent_height: 5000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_try_validate_header_chain_expected_tip_hash() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+        let tip_hash = bitcoin_core_rs::get_block_hash(&TEST_HEADERS[9].1).unwrap();
+
+        assert_eq!(
+            try_validate_header_chain(
+                0,
+                genesis_header,
+                genesis_header,
+                &header_chain,
+                Some(tip_hash)
+            ),
+            Ok(())
+        );
+
+        let wrong_tip_hash = bitcoin_core_rs::get_block_hash(&TEST_HEADERS[8].1).unwrap();
+        assert_eq!(
+            try_validate_header_chain(
+                0,
+                genesis_header,
+                genesis_header,
+                &header_chain,
+                Some(wrong_tip_hash)
+            ),
+            Err(HeaderChainError::TipHashMismatch {
+                expected: wrong_tip_hash,
+                found: tip_hash,
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_validate_header_chain_with_banned_hashes() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let header_chain: Vec<Header> = TEST_HEADERS[1..10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        assert_eq!(
+            try_validate_header_chain_with_banned_hashes(
+                0,
+                genesis_header,
+                genesis_header,
+                &header_chain,
+                &HashSet::new()
+            ),
+            Ok(())
+        );
+
+        let banned_hash = bitcoin_core_rs::get_block_hash(&TEST_HEADERS[5].1).unwrap();
+        assert_eq!(
+            try_validate_header_chain_with_banned_hashes(
+                0,
+                genesis_header,
+                genesis_header,
+                &header_chain,
+                &HashSet::from([banned_hash])
+            ),
+            Err(HeaderChainError::BannedBlock {
+                index: 4,
+                hash: banned_hash,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_header_chain_collect_errors_reports_all() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let mut header_chain: Vec<Header> = TEST_HEADERS[1..=2020]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        // invalidate the PoW of block 3, and the difficulty update at block 2016, relinking
+        // every following header so the chain stays connected
+        header_chain[2].0[76..80].copy_from_slice(&[0; 4]);
+        header_chain[2015].0[72..76].copy_from_slice(&[0xff, 0xff, 0x00, 0x1c]);
+        for i in 3..header_chain.len() {
+            let previous = header_chain[i - 1];
+            relink(&mut header_chain[i], &previous);
+        }
+
+        let errors =
+            validate_header_chain_collect_errors(0, genesis_header, genesis_header, &header_chain);
+
+        assert_eq!(
+            errors[0],
+            HeaderChainError::InvalidProofOfWork { height: 3 }
+        );
+        assert!(errors.contains(&HeaderChainError::InvalidWorkRequirement { height: 2016 }));
+        // relinked headers no longer meet their target either
+        assert!(errors.contains(&HeaderChainError::InvalidProofOfWork { height: 2020 }));
+        assert!(!errors
+            .iter()
+            .any(|e| matches!(e, HeaderChainError::BrokenLink { .. })));
+
+        // the first error is the same one validate_header_chain would fail on
+        assert_eq!(
+            try_validate_header_chain(0, genesis_header, genesis_header, &header_chain, None),
+            Err(HeaderChainError::InvalidProofOfWork { height: 3 })
+        );
+    }
+
+    #[test]
+    fn test_validate_header_chain_collect_errors_stops_at_broken_link() {
+        let genesis_header = &Header(TEST_HEADERS[0].1);
+        let mut header_chain: Vec<Header> = TEST_HEADERS[1..10]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        // invalidate block 3's PoW and relink only block 4, so block 5 no longer connects
+        header_chain[2].0[76..80].copy_from_slice(&[0; 4]);
+        let previous = header_chain[2];
+        relink(&mut header_chain[3], &previous);
+
+        let errors =
+            validate_header_chain_collect_errors(0, genesis_header, genesis_header, &header_chain);
+
+        assert_eq!(
+            errors,
+            vec![
+                HeaderChainError::InvalidProofOfWork { height: 3 },
+                HeaderChainError::InvalidProofOfWork { height: 4 },
+                HeaderChainError::BrokenLink { height: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_chain_validation_over_thousands_of_headers() {
+        let genesis_header = Header(TEST_HEADERS[0].1);
+        let mut header_chain: Vec<Header> = TEST_HEADERS[1..=4500]
+            .iter()
+            .map(|(_, header)| Header(*header))
+            .collect();
+
+        // crosses two retargets
+        assert!(validate_header_chain_collect_errors(
+            0,
+            &genesis_header,
+            &genesis_header,
+            &header_chain
+        )
+        .is_empty());
+        let mut validator = HeaderChainValidator::new(0, genesis_header, genesis_header);
+        validator.validate(&header_chain).unwrap();
+        assert_eq!(validator.tip(), header_chain.last().unwrap());
+
+        header_chain[3000].0[76..80].copy_from_slice(&[0; 4]);
+        assert_eq!(
+            validate_header_chain_collect_errors(
+                0,
+                &genesis_header,
+                &genesis_header,
+                &header_chain
+            ),
+            vec![
+                HeaderChainError::InvalidProofOfWork { height: 3001 },
+                HeaderChainError::BrokenLink { height: 3002 },
+            ]
+        );
+        assert_eq!(
+            try_validate_header_chain(0, &genesis_header, &genesis_header, &header_chain, None),
+            HeaderChainValidator::new(0, genesis_header, genesis_header).validate(&header_chain)
+        );
+    }
+
+    // Grinds the nonce until `header` meets the target encoded in its bits
+    fn mine(header: &mut Header) {
+        let target = bitcoin_core_rs::bits_to_target(&header.0[72..76].try_into().unwrap());
+        for nonce in 0u32.. {
+            header.0[76..80].copy_from_slice(&nonce.to_le_bytes());
+            if bitcoin_core_rs::check_proof_of_work_with_target(header.as_bytes(), &target) {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_chain_validator_with_custom_retarget_interval() {
+        const RETARGET_INTERVAL: u32 = 10;
+        // easy enough to mine quickly, hard enough that retargeting doesn't overflow
+        let pow_limit =
+            U256::from_be_hex("0003ffff00000000000000000000000000000000000000000000000000000000");
+
+        let mut parent = Header(TEST_HEADERS[0].1);
+        parent.0[72..76].copy_from_slice(&0x1f03ffffu32.to_le_bytes());
+        mine(&mut parent);
+
+        let next_header = |previous: &Header, bits: [u8; 4]| {
+            let mut header = *previous;
+            header.0[68..72].copy_from_slice(&(previous.time() + 600).to_le_bytes());
+            header.0[72..76].copy_from_slice(&bits);
+            relink(&mut header, previous);
+            mine(&mut header);
+            header
+        };
+        let mut header_chain: Vec<Header> = Vec::new();
+        for _ in 1..RETARGET_INTERVAL {
+            let previous = *header_chain.last().unwrap_or(&parent);
+            header_chain.push(next_header(&previous, parent.0[72..76].try_into().unwrap()));
+        }
+        let last = *header_chain.last().unwrap();
+
+        // the period ran faster than its target timespan, so height 10 must retarget
+        let retargeted_bits = bitcoin_core_rs::calculate_next_work_required_with_params(
+            parent.as_bytes(),
+            last.as_bytes(),
+            &pow_limit,
+            RETARGET_INTERVAL,
+        );
+        assert_ne!(retargeted_bits, parent.0[72..76]);
+        let retargeted = next_header(&last, retargeted_bits);
+        let stale = next_header(&last, parent.0[72..76].try_into().unwrap());
+
+        let validator = || {
+            HeaderChainValidator::new(0, parent, parent)
+                .with_pow_limit(pow_limit)
+                .with_retarget_interval(RETARGET_INTERVAL)
+        };
+        let mut retargeted_chain = header_chain.clone();
+        retargeted_chain.push(retargeted);
+        let mut validator_retargeted = validator();
+        validator_retargeted.validate(&retargeted_chain).unwrap();
+        assert_eq!(validator_retargeted.retarget_header(), &retargeted);
+
+        let mut stale_chain = header_chain;
+        stale_chain.push(stale);
+        assert_eq!(
+            validator().validate(&stale_chain),
+            Err(HeaderChainError::InvalidWorkRequirement { height: 10 })
+        );
+
+        // the stateless path agrees given the same params
+        let params = ChainParams {
+            pow_limit,
+            retarget_interval: RETARGET_INTERVAL,
+        };
+        let validate_with_params = |chain: &[Header]| {
+            try_validate_header_chain_with_params(0, &parent, &parent, chain, None, &params)
+        };
+        assert_eq!(validate_with_params(&retargeted_chain), Ok(()));
+        assert_eq!(
+            validate_with_params(&stale_chain),
+            Err(HeaderChainError::InvalidWorkRequirement { height: 10 })
+        );
+        // including from a parent that starts a custom-length period
+        assert_eq!(
+            try_validate_header_chain_with_params(
+                10,
+                &retargeted,
+                &parent,
+                &[next_header(&retargeted, retargeted_bits)],
+                None,
+                &params
+            ),
+            Err(HeaderChainError::RetargetMismatch { parent_height: 10 })
+        );
+        // height 10 is mid-period at the default interval, keeping the difficulty is valid there
+        HeaderChainValidator::new(0, parent, parent)
+            .with_pow_limit(pow_limit)
+            .validate(&stale_chain)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_header_chain_validator_with_relaxed_pow_limit() {
+        // regtest's limit, far easier than mainnet's
+        let relaxed_pow_limit =
+            U256::from_be_hex("7fffff0000000000000000000000000000000000000000000000000000000000");
+        let relaxed_bits = 0x207fffffu32.to_le_bytes();
+
+        let mut parent = Header(TEST_HEADERS[0].1);
+        parent.0[72..76].copy_from_slice(&relaxed_bits);
+        mine(&mut parent);
+
+        let mut header_chain = Vec::new();
+        let mut previous = parent;
+        for _ in 0..5 {
+            let mut header = previous;
+            relink(&mut header, &previous);
+            mine(&mut header);
+            header_chain.push(header);
+            previous = header;
+        }
+
+        assert_eq!(
+            HeaderChainValidator::new(0, parent, parent).validate(&header_chain),
+            Err(HeaderChainError::InvalidProofOfWork { height: 1 })
+        );
+
+        let mut validator =
+            HeaderChainValidator::new(0, parent, parent).with_pow_limit(relaxed_pow_limit);
+        validator.validate(&header_chain).unwrap();
+        assert_eq!(validator.height(), 5);
+    }
+
+    #[test]
+    fn test_pow_limit_applied_by_both_validation_paths() {
+        let relaxed_pow_limit =
+            U256::from_be_hex("7fffff0000000000000000000000000000000000000000000000000000000000");
+        let mut parent = Header(TEST_HEADERS[0].1);
+        parent.0[72..76].copy_from_slice(&0x207fffffu32.to_le_bytes());
+        mine(&mut parent);
+        // meets the target in its bits, but that target is over mainnet's limit
+        let mut header = parent;
+        relink(&mut header, &parent);
+        mine(&mut header);
+        let header_chain = [header];
+
+        assert_eq!(
+            try_validate_header_chain(0, &parent, &parent, &header_chain, None),
+            Err(HeaderChainError::InvalidProofOfWork { height: 1 })
+        );
+        assert_eq!(
+            try_validate_header_chain(0, &parent, &parent, &header_chain, None),
+            HeaderChainValidator::new(0, parent, parent).validate(&header_chain)
+        );
+
+        let relaxed = ChainParams {
+            pow_limit: relaxed_pow_limit,
+            ..ChainParams::default()
+        };
+        assert_eq!(
+            try_validate_header_chain_with_params(
+                0,
+                &parent,
+                &parent,
+                &header_chain,
+                None,
+                &relaxed
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            HeaderChainValidator::new(0, parent, parent)
+                .with_pow_limit(relaxed_pow_limit)
+                .validate(&header_chain),
+            Ok(())
+        );
+    }
 }
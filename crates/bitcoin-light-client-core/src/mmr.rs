@@ -1,6 +1,7 @@
 // This is a bare bones implementation of an MMR for use in circuit validation
 // Actual storage of leaves and proof generation is left to the client
 use crate::hasher::{Digest, Hasher};
+use crate::leaves::BlockLeaf;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug};
 
@@ -177,6 +178,20 @@ pub fn bag_peaks<H: Hasher>(peaks: &[Digest]) -> Option<Digest> {
 
 pub type CompactMerkleMountainRange<H> = MerkleMountainRange<H, CompactMMR>;
 
+/// Root of the MMR with `peaks` and `leaf_count` after appending `new_leaves`, computed without
+/// the full MMR. Panics if the peak count doesn't match `leaf_count` or the result is empty.
+pub fn compute_root_after_append<H: Hasher>(
+    peaks: &[Digest],
+    leaf_count: u32,
+    new_leaves: &[BlockLeaf],
+) -> Digest {
+    let mut mmr = CompactMerkleMountainRange::<H>::from_peaks(peaks, leaf_count);
+    for leaf in new_leaves {
+        mmr.append(&leaf.hash::<H>());
+    }
+    mmr.get_root()
+}
+
 #[derive(Clone, Serialize, Deserialize, Default)]
 pub struct MMRProof {
     pub leaf_hash: Digest,
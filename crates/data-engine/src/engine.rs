@@ -8,10 +8,12 @@ use alloy::{
 use bitcoin_light_client_core::{
     hasher::{Digest, Keccak256Hasher},
     leaves::{decompress_block_leaves, BlockLeaf},
+    ProvenLeaf,
 };
 use eyre::Result;
 use futures_util::stream::StreamExt;
 use rift_sdk::checkpoint_mmr::CheckpointedBlockTree;
+use rift_sdk::indexed_mmr::MmrRoot;
 use rift_sdk::DatabaseLocation;
 use sol_bindings::{
     RiftExchange,
@@ -252,6 +254,16 @@ impl ContractDataEngine {
         }
     }
 
+    /// Proof that the block leaf hashing to `leaf_hash` is in the light client's MMR, verifiable
+    /// against `get_mmr_root`. `None` if the light client doesn't contain the leaf.
+    pub async fn prove_inclusion(&self, leaf_hash: [u8; 32]) -> Result<Option<ProvenLeaf>> {
+        let checkpointed_block_tree = self.checkpointed_block_tree.read().await;
+        Ok(checkpointed_block_tree
+            .get_proven_leaf_by_leaf_hash(&leaf_hash)
+            .await?
+            .map(|(leaf, proof)| ProvenLeaf { leaf, proof }))
+    }
+
     // Delegate method that provides read access to the mmr
     pub async fn get_leaf_count(&self) -> Result<usize> {
         let checkpointed_block_tree = self.checkpointed_block_tree.read().await;
@@ -261,11 +273,12 @@ impl ContractDataEngine {
             .map_err(|e| eyre::eyre!(e))
     }
 
-    pub async fn get_mmr_root(&self) -> Result<Digest> {
+    pub async fn get_mmr_root(&self) -> Result<MmrRoot> {
         let checkpointed_block_tree = self.checkpointed_block_tree.read().await;
         checkpointed_block_tree
             .get_root()
             .await
+            .map(MmrRoot::from)
             .map_err(|e| eyre::eyre!(e))
     }
 
@@ -283,6 +296,16 @@ impl ContractDataEngine {
     ) -> Result<Option<ChainAwareDeposit>> {
         get_deposit_by_id(&self.swap_database_connection, deposit_id).await
     }
+
+    /// Returns the height of the block with the given hash if it is part of the light client's
+    /// chain, or `None` otherwise.
+    pub async fn contains_block(&self, block_hash: [u8; 32]) -> Result<Option<u32>> {
+        let checkpointed_block_tree = self.checkpointed_block_tree.read().await;
+        Ok(checkpointed_block_tree
+            .get_leaf_by_block_hash(&block_hash)
+            .await?
+            .map(|(_, leaf)| leaf.height))
+    }
 }
 
 fn get_qualified_swaps_database_path(database_location: String) -> String {
@@ -572,3 +595,48 @@ async fn handle_bitcoin_light_client_updated_event(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_leaves() -> Vec<BlockLeaf> {
+        (0..5u8)
+            .map(|i| BlockLeaf {
+                block_hash: [i + 1; 32],
+                height: i as u32,
+                cumulative_chainwork: [i; 32],
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_contains_block() -> Result<()> {
+        let engine = ContractDataEngine::seed(&DatabaseLocation::InMemory, test_leaves()).await?;
+
+        assert_eq!(engine.contains_block([1; 32]).await?, Some(0));
+        assert_eq!(engine.contains_block([4; 32]).await?, Some(3));
+        assert_eq!(engine.contains_block([0xaa; 32]).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prove_inclusion() -> Result<()> {
+        let leaves = test_leaves();
+        let engine = ContractDataEngine::seed(&DatabaseLocation::InMemory, leaves.clone()).await?;
+        let root = engine.get_mmr_root().await?;
+
+        let proven_leaf = engine
+            .prove_inclusion(leaves[3].hash::<Keccak256Hasher>())
+            .await?
+            .expect("leaf is in the light client");
+        assert_eq!(proven_leaf.leaf, leaves[3]);
+        assert_eq!(proven_leaf.proof.leaf_index, 3);
+        assert!(bitcoin_light_client_core::mmr::verify_mmr_proof::<
+            Keccak256Hasher,
+        >(&root.into(), &proven_leaf.proof));
+
+        assert!(engine.prove_inclusion([0xaa; 32]).await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use bitcoin_light_client_core::{leaves::BlockLeaf, ProvenLeaf};
+use eyre::Result;
+use rift_sdk::indexed_mmr::MmrRoot;
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::warn;
+
+use crate::engine::ContractDataEngine;
+
+/// Read access to the light client's state
+pub trait LightClientState: Send + Sync {
+    fn get_mmr_root(&self) -> impl Future<Output = Result<MmrRoot>> + Send;
+
+    fn get_tip_leaf(&self) -> impl Future<Output = Result<BlockLeaf>> + Send;
+
+    fn prove_inclusion(
+        &self,
+        leaf_hash: [u8; 32],
+    ) -> impl Future<Output = Result<Option<ProvenLeaf>>> + Send;
+}
+
+impl LightClientState for ContractDataEngine {
+    async fn get_mmr_root(&self) -> Result<MmrRoot> {
+        ContractDataEngine::get_mmr_root(self).await
+    }
+
+    async fn get_tip_leaf(&self) -> Result<BlockLeaf> {
+        Ok(self.get_tip_proof().await?.0)
+    }
+
+    async fn prove_inclusion(&self, leaf_hash: [u8; 32]) -> Result<Option<ProvenLeaf>> {
+        ContractDataEngine::prove_inclusion(self, leaf_hash).await
+    }
+}
+
+/// Follows the light client's state without ever writing to it: polls the MMR root, publishing
+/// changes to subscribers, and answers tip and inclusion queries. Polling stops when the
+/// follower is dropped.
+pub struct LightClientFollower<S> {
+    state: Arc<S>,
+    root_rx: watch::Receiver<MmrRoot>,
+    poll_handle: JoinHandle<()>,
+}
+
+impl<S: LightClientState + 'static> LightClientFollower<S> {
+    pub async fn new(state: Arc<S>, poll_interval: Duration) -> Result<Self> {
+        let (root_tx, root_rx) = watch::channel(state.get_mmr_root().await?);
+        let poll_state = state.clone();
+        let poll_handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(poll_interval);
+            loop {
+                ticker.tick().await;
+                match poll_state.get_mmr_root().await {
+                    Ok(root) => {
+                        root_tx.send_if_modified(|current| {
+                            let changed = *current != root;
+                            *current = root;
+                            changed
+                        });
+                    }
+                    Err(e) => warn!(message = "Failed to poll light client root", error = %e),
+                }
+            }
+        });
+        Ok(Self {
+            state,
+            root_rx,
+            poll_handle,
+        })
+    }
+
+    /// Root as of the last poll
+    pub fn current_root(&self) -> MmrRoot {
+        *self.root_rx.borrow()
+    }
+
+    /// Receiver notified whenever the light client's root changes
+    pub fn subscribe(&self) -> watch::Receiver<MmrRoot> {
+        self.root_rx.clone()
+    }
+
+    pub async fn tip_leaf(&self) -> Result<BlockLeaf> {
+        self.state.get_tip_leaf().await
+    }
+
+    /// Proof that the block leaf hashing to `leaf_hash` is in the light client's MMR, `None` if
+    /// the light client doesn't contain the leaf
+    pub async fn prove_inclusion(&self, leaf_hash: [u8; 32]) -> Result<Option<ProvenLeaf>> {
+        self.state.prove_inclusion(leaf_hash).await
+    }
+}
+
+impl<S> Drop for LightClientFollower<S> {
+    fn drop(&mut self) {
+        self.poll_handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+
+    struct MockLightClient {
+        root: Mutex<MmrRoot>,
+    }
+
+    impl LightClientState for MockLightClient {
+        async fn get_mmr_root(&self) -> Result<MmrRoot> {
+            Ok(*self.root.lock().await)
+        }
+
+        async fn get_tip_leaf(&self) -> Result<BlockLeaf> {
+            Ok(BlockLeaf::new([1; 32], 100, [0; 32]))
+        }
+
+        async fn prove_inclusion(&self, _leaf_hash: [u8; 32]) -> Result<Option<ProvenLeaf>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follower_reflects_root_changes() {
+        let light_client = Arc::new(MockLightClient {
+            root: Mutex::new(MmrRoot::from([1u8; 32])),
+        });
+        let follower = LightClientFollower::new(light_client.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        let mut root_rx = follower.subscribe();
+        assert_eq!(follower.current_root(), MmrRoot::from([1u8; 32]));
+
+        *light_client.root.lock().await = MmrRoot::from([2u8; 32]);
+        tokio::time::timeout(Duration::from_secs(5), root_rx.changed())
+            .await
+            .expect("Root change was not published")
+            .unwrap();
+        assert_eq!(*root_rx.borrow(), MmrRoot::from([2u8; 32]));
+        assert_eq!(follower.current_root(), MmrRoot::from([2u8; 32]));
+
+        assert_eq!(follower.tip_leaf().await.unwrap().height, 100);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod db;
 pub mod engine;
+pub mod follower;
 pub mod models;
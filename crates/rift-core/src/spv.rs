@@ -1,8 +1,12 @@
+use bitcoin::Transaction;
 use bitcoin_core_rs::sha256;
 use serde::{Deserialize, Serialize};
 
 pub type Sha256Digest = [u8; 32];
 
+// OP_RETURN OP_PUSHBYTES_36 followed by the 0xaa21a9ed commitment header (BIP141)
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
 #[derive(Default, Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct MerkleProofStep {
     pub hash: Sha256Digest,
@@ -94,6 +98,50 @@ pub fn generate_bitcoin_txn_hash(txn_data: &[u8]) -> Sha256Digest {
     sha256(&sha256(txn_data))
 }
 
+/// Validate that the coinbase commits to the block's witness merkle root (BIP141).
+/// The commitment is the last coinbase output matching the commitment header, and must equal
+/// sha256d(witness_root || witness reserved value), where the reserved value is the single
+/// 32 byte item in the coinbase input's witness.
+pub fn validate_segwit_commitment(
+    coinbase_tx: &Transaction,
+    // natural byte order
+    witness_root: Sha256Digest,
+) -> Result<(), &'static str> {
+    if !coinbase_tx.is_coinbase() {
+        return Err("Transaction is not a coinbase");
+    }
+
+    let commitment_script = coinbase_tx
+        .output
+        .iter()
+        .rev()
+        .map(|output| output.script_pubkey.as_bytes())
+        .find(|script| {
+            script.len() >= 38
+                && script[..WITNESS_COMMITMENT_HEADER.len()] == WITNESS_COMMITMENT_HEADER
+        })
+        .ok_or("Coinbase has no witness commitment output")?;
+
+    let witness = &coinbase_tx.input[0].witness;
+    if witness.len() != 1 {
+        return Err("Coinbase witness must contain exactly one item");
+    }
+    let witness_reserved_value: [u8; 32] = witness
+        .nth(0)
+        .and_then(|item| item.try_into().ok())
+        .ok_or("Coinbase witness reserved value must be 32 bytes")?;
+
+    let mut commitment_preimage = [0u8; 64];
+    commitment_preimage[..32].copy_from_slice(&witness_root);
+    commitment_preimage[32..].copy_from_slice(&witness_reserved_value);
+
+    if sha256(&sha256(&commitment_preimage)) != commitment_script[6..38] {
+        return Err("Witness commitment does not match witness root");
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +169,40 @@ mod tests {
             verify_bitcoin_txn_merkle_proof(block_merkle_root, txn_hashes[0], &proof);
         }
     }
+
+    #[test]
+    fn test_validate_segwit_commitment_against_block_sample() {
+        for block in TEST_BLOCKS.iter() {
+            let witness_root = block.witness_root().unwrap().to_raw_hash().to_byte_array();
+            let coinbase = &block.txdata[0];
+
+            assert_eq!(validate_segwit_commitment(coinbase, witness_root), Ok(()));
+
+            let mut invalid_witness_root = witness_root;
+            invalid_witness_root[0] ^= 0xff;
+            assert_eq!(
+                validate_segwit_commitment(coinbase, invalid_witness_root),
+                Err("Witness commitment does not match witness root")
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_segwit_commitment_missing_commitment() {
+        let block = &TEST_BLOCKS[0];
+        let witness_root = block.witness_root().unwrap().to_raw_hash().to_byte_array();
+
+        let mut coinbase = block.txdata[0].clone();
+        coinbase.output.retain(|output| {
+            !output
+                .script_pubkey
+                .as_bytes()
+                .starts_with(&WITNESS_COMMITMENT_HEADER)
+        });
+
+        assert_eq!(
+            validate_segwit_commitment(&coinbase, witness_root),
+            Err("Coinbase has no witness commitment output")
+        );
+    }
 }
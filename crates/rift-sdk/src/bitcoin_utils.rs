@@ -13,15 +13,21 @@ use backoff::future::retry;
 use backoff::Error as BackoffError;
 use backoff::ExponentialBackoff;
 use bitcoin_light_client_core::leaves::BlockLeaf;
+use bitcoin_light_client_core::light_client::{
+    calculate_cumulative_work, try_validate_header_chain, Header as LightClientHeader,
+    HeaderChainCheckpoint, HeaderChainValidator as StatefulHeaderChainValidator,
+};
 use bitcoincore_rpc_async::jsonrpc::Transport;
 use bitcoincore_rpc_async::jsonrpc::{Request, Response};
 use bitcoincore_rpc_async::{Auth, Client as BitcoinClient, RpcApi};
+use crypto_bigint::U256;
 use futures::stream::TryStreamExt;
 use futures::Future;
 use futures::{stream, StreamExt};
 use std::ops::Deref;
 use std::str::FromStr;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use async_trait::async_trait;
 use reqwest::{Client as ReqwestClient, Url};
@@ -312,7 +318,11 @@ impl HeaderChainValidator for Vec<Header> {
     fn validate_header_chain(&self) -> Result<(), RiftSdkError> {
         for i in 1..self.len() {
             if self[i].prev_blockhash != self[i - 1].block_hash() {
-                return Err(RiftSdkError::HeaderChainValidationFailed);
+                return Err(RiftSdkError::HeaderChainValidationFailed(format!(
+                    "header {} does not extend header {}",
+                    i,
+                    i - 1
+                )));
             }
         }
         Ok(())
@@ -323,7 +333,11 @@ impl HeaderChainValidator for Vec<GetBlockHeaderResult> {
     fn validate_header_chain(&self) -> Result<(), RiftSdkError> {
         for i in 1..self.len() {
             if self[i].previous_block_hash.unwrap() != self[i - 1].hash {
-                return Err(RiftSdkError::HeaderChainValidationFailed);
+                return Err(RiftSdkError::HeaderChainValidationFailed(format!(
+                    "header {} does not extend header {}",
+                    i,
+                    i - 1
+                )));
             }
         }
         Ok(())
@@ -813,3 +827,287 @@ impl BitcoinClientExt for AsyncBitcoinClient {
         Ok(headers)
     }
 }
+
+/// Downloads the headers after `checkpoint` up to and including `end_block_height` and validates
+/// them as a continuation of the checkpoint under consensus rules. Returns the validated headers
+/// and the cumulative chainwork at `end_block_height`.
+pub async fn download_and_validate_headers(
+    btc_rpc: &(impl BitcoinClientExt + Sync),
+    checkpoint: &HeaderChainCheckpoint,
+    end_block_height: u32,
+    concurrency_limit: usize,
+) -> crate::errors::Result<(Vec<LightClientHeader>, U256)> {
+    if end_block_height <= checkpoint.height {
+        return Err(RiftSdkError::HeaderChainValidationFailed(format!(
+            "End height {} is not after checkpoint height {}",
+            end_block_height, checkpoint.height
+        )));
+    }
+
+    let headers = btc_rpc
+        .get_headers_from_block_range(
+            checkpoint.height + 1,
+            end_block_height,
+            concurrency_limit,
+            // linkage to the checkpoint is checked by the header chain validation below
+            None,
+        )
+        .await?
+        .iter()
+        .map(|header| {
+            LightClientHeader::try_from(
+                bitcoincore_rpc_async::bitcoin::consensus::encode::serialize(header),
+            )
+            .map_err(|e| RiftSdkError::HeaderChainValidationFailed(e.to_string()))
+        })
+        .collect::<crate::errors::Result<Vec<_>>>()?;
+
+    try_validate_header_chain(
+        checkpoint.height,
+        &checkpoint.header,
+        &checkpoint.retarget_header,
+        &headers,
+        None,
+    )
+    .map_err(|e| RiftSdkError::HeaderChainValidationFailed(e.to_string()))?;
+
+    let (_, cumulative_work) = calculate_cumulative_work(checkpoint.cumulative_work, &headers);
+    Ok((headers, cumulative_work))
+}
+
+/// Validates a stream of concatenated 80 byte headers as a continuation of `validator`'s tip,
+/// without buffering more than one header, calling `on_header` with the height of each header
+/// as it's validated. Returns the number of headers validated. Errors report the byte offset of
+/// the offending header, `validator` is left at the last valid header.
+pub async fn validate_headers_from_reader(
+    mut reader: impl AsyncRead + Unpin,
+    validator: &mut StatefulHeaderChainValidator,
+    mut on_header: impl FnMut(u32, &LightClientHeader),
+) -> crate::errors::Result<u64> {
+    let mut buffer = [0u8; 80];
+    let mut header_count = 0u64;
+    loop {
+        let offset = header_count * 80;
+        // a single read can return fewer bytes than a header
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = reader.read(&mut buffer[filled..]).await.map_err(|e| {
+                RiftSdkError::HeaderChainValidationFailed(format!(
+                    "failed to read at byte offset {}: {}",
+                    offset + filled as u64,
+                    e
+                ))
+            })?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+
+        match filled {
+            0 => return Ok(header_count),
+            80 => {}
+            _ => {
+                return Err(RiftSdkError::HeaderChainValidationFailed(format!(
+                    "stream ended with a partial {} byte header at byte offset {}",
+                    filled, offset
+                )))
+            }
+        }
+
+        let header = LightClientHeader(buffer);
+        validator.validate_next(&header).map_err(|e| {
+            RiftSdkError::HeaderChainValidationFailed(format!("at byte offset {}: {}", offset, e))
+        })?;
+        on_header(validator.height(), &header);
+        header_count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_light_client_core::leaves::get_genesis_leaf;
+    use bitcoincore_rpc_async::bitcoin::consensus::encode::deserialize;
+    use test_data_utils::TEST_HEADERS;
+
+    // Serves fixture headers by height, standing in for a bitcoin node
+    struct FixtureBitcoinClient {
+        headers: Vec<Header>,
+    }
+
+    impl FixtureBitcoinClient {
+        fn new() -> Self {
+            Self {
+                headers: TEST_HEADERS
+                    .iter()
+                    .map(|(_, header)| deserialize(header).unwrap())
+                    .collect(),
+            }
+        }
+    }
+
+    fn not_served() -> RiftSdkError {
+        RiftSdkError::BitcoinRpcError("not served by the fixture client".to_string())
+    }
+
+    #[async_trait::async_trait]
+    impl BitcoinClientExt for FixtureBitcoinClient {
+        async fn get_leaves_from_block_range(
+            &self,
+            _start_block_height: u32,
+            _end_block_height: u32,
+            _concurrency_limit: usize,
+            _expected_parent: Option<[u8; 32]>,
+        ) -> crate::errors::Result<Vec<BlockLeaf>> {
+            Err(not_served())
+        }
+
+        async fn get_headers_from_block_range(
+            &self,
+            start_block_height: u32,
+            end_block_height: u32,
+            _concurrency_limit: usize,
+            _expected_parent: Option<[u8; 32]>,
+        ) -> crate::errors::Result<Vec<Header>> {
+            Ok(self.headers[start_block_height as usize..=end_block_height as usize].to_vec())
+        }
+
+        async fn get_blocks_from_leaves(
+            &self,
+            _leaves: &[BlockLeaf],
+            _concurrency_limit: usize,
+        ) -> crate::errors::Result<Vec<Block>> {
+            Err(not_served())
+        }
+
+        async fn get_chain_tips(&self) -> crate::errors::Result<Vec<ChainTip>> {
+            Err(not_served())
+        }
+
+        async fn get_block_header_by_height(&self, _height: u32) -> crate::errors::Result<Header> {
+            Err(not_served())
+        }
+
+        async fn get_block_header_info_by_height(
+            &self,
+            _height: u32,
+        ) -> crate::errors::Result<GetBlockHeaderResult> {
+            Err(not_served())
+        }
+
+        async fn find_oldest_block_before_timestamp(
+            &self,
+            _target_timestamp: u64,
+        ) -> crate::errors::Result<u32> {
+            Err(not_served())
+        }
+
+        async fn get_headers_from_hashes(
+            &self,
+            _hashes: &[BlockHash],
+            _concurrency_limit: usize,
+        ) -> crate::errors::Result<Vec<Header>> {
+            Err(not_served())
+        }
+    }
+
+    fn genesis_checkpoint() -> HeaderChainCheckpoint {
+        let genesis_header = LightClientHeader(TEST_HEADERS[0].1);
+        HeaderChainCheckpoint {
+            height: 0,
+            header: genesis_header,
+            retarget_header: genesis_header,
+            cumulative_work: get_genesis_leaf().chainwork_as_u256(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_and_validate_headers() {
+        let (headers, cumulative_work) = download_and_validate_headers(
+            &FixtureBitcoinClient::new(),
+            &genesis_checkpoint(),
+            100,
+            4,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(headers.len(), 100);
+        assert_eq!(headers[0].0, TEST_HEADERS[1].1);
+        assert_eq!(headers[99].0, TEST_HEADERS[100].1);
+        // every block before the first retarget has the minimum difficulty work of 0x100010001
+        assert_eq!(cumulative_work, U256::from_u64(0x100010001 * 101));
+    }
+
+    #[tokio::test]
+    async fn test_download_and_validate_headers_rejects_invalid_chain() {
+        let mut client = FixtureBitcoinClient::new();
+        client.headers.swap(50, 51);
+
+        let result = download_and_validate_headers(&client, &genesis_checkpoint(), 100, 4).await;
+
+        assert!(matches!(
+            result,
+            Err(RiftSdkError::HeaderChainValidationFailed(_))
+        ));
+    }
+
+    fn genesis_validator() -> StatefulHeaderChainValidator {
+        let genesis_header = LightClientHeader(TEST_HEADERS[0].1);
+        StatefulHeaderChainValidator::new(0, genesis_header, genesis_header)
+    }
+
+    #[tokio::test]
+    async fn test_validate_headers_from_reader_matches_batch() {
+        let headers: Vec<LightClientHeader> = TEST_HEADERS[1..]
+            .iter()
+            .map(|(_, header)| LightClientHeader(*header))
+            .collect();
+        let bytes: Vec<u8> = headers.iter().flat_map(|header| header.0).collect();
+
+        let mut batch_validator = genesis_validator();
+        batch_validator.validate(&headers).unwrap();
+
+        // a small buffer forces reads that split headers
+        let reader = tokio::io::BufReader::with_capacity(7, bytes.as_slice());
+        let mut streaming_validator = genesis_validator();
+        let mut heights = Vec::new();
+        let header_count =
+            validate_headers_from_reader(reader, &mut streaming_validator, |height, _| {
+                heights.push(height)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(header_count, headers.len() as u64);
+        assert_eq!(heights, (1..=headers.len() as u32).collect::<Vec<_>>());
+        assert_eq!(streaming_validator.snapshot(), batch_validator.snapshot());
+    }
+
+    #[tokio::test]
+    async fn test_validate_headers_from_reader_reports_byte_offset() {
+        let mut bytes: Vec<u8> = TEST_HEADERS[1..=100]
+            .iter()
+            .flat_map(|(_, header)| *header)
+            .collect();
+        // break the prev block hash of the header at height 51
+        bytes[50 * 80 + 4] ^= 1;
+
+        let mut validator = genesis_validator();
+        let err = validate_headers_from_reader(bytes.as_slice(), &mut validator, |_, _| {})
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("at byte offset 4000"));
+        assert_eq!(validator.height(), 50);
+
+        let mut validator = genesis_validator();
+        let err = validate_headers_from_reader(&bytes[..85], &mut validator, |_, _| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RiftSdkError::HeaderChainValidationFailed(_)));
+        assert!(err
+            .to_string()
+            .contains("partial 5 byte header at byte offset 80"));
+    }
+}
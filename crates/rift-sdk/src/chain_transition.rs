@@ -0,0 +1,93 @@
+use bitcoin_light_client_core::{hasher::Hasher, ChainTransition};
+
+use crate::errors::{Result, RiftSdkError};
+
+/// Serializes `transition` so an expensive-to-build transition can be persisted and reused
+/// after a restart
+pub fn serialize_chain_transition(transition: &ChainTransition) -> Vec<u8> {
+    serde_json::to_vec(transition).expect("Chain transition serialization is infallible")
+}
+
+/// Reconstructs a transition produced by `serialize_chain_transition`, rejecting it if it is not
+/// internally consistent (see `ChainTransition::validate_consistency`)
+pub fn deserialize_chain_transition<H: Hasher>(bytes: &[u8]) -> Result<ChainTransition> {
+    let transition: ChainTransition = serde_json::from_slice(bytes)
+        .map_err(|e| RiftSdkError::InvalidChainTransition(format!("Failed to deserialize: {e}")))?;
+    transition
+        .validate_consistency::<H>()
+        .map_err(|e| RiftSdkError::InvalidChainTransition(e.to_string()))?;
+    Ok(transition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed_mmr::IndexedMMR;
+    use crate::DatabaseLocation;
+    use bitcoin_light_client_core::hasher::Keccak256Hasher;
+    use bitcoin_light_client_core::leaves::get_genesis_leaf;
+    use bitcoin_light_client_core::light_client::Header;
+    use bitcoin_light_client_core::{ProvenLeaf, VerifiedBlock};
+    use test_data_utils::TEST_HEADERS;
+
+    async fn genesis_extension_transition() -> ChainTransition {
+        let mut mmr = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory)
+            .await
+            .unwrap();
+        mmr.append(&get_genesis_leaf()).await.unwrap();
+        let genesis_proven_leaf = ProvenLeaf {
+            leaf: get_genesis_leaf(),
+            proof: mmr.get_circuit_proof(0, None).await.unwrap(),
+        };
+        let genesis_block = VerifiedBlock {
+            header: Header(TEST_HEADERS[0].1),
+            mmr_data: genesis_proven_leaf.clone(),
+        };
+
+        ChainTransition::new(
+            mmr.get_root().await.unwrap(),
+            mmr.get_bagged_peak().await.unwrap(),
+            genesis_block.clone(),
+            genesis_block,
+            genesis_proven_leaf,
+            mmr.get_peaks(None).await.unwrap(),
+            vec![],
+            TEST_HEADERS[1..4]
+                .iter()
+                .map(|(_, header)| Header(*header))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_chain_transition_serialization_roundtrip() {
+        let transition = genesis_extension_transition().await;
+
+        let restored = deserialize_chain_transition::<Keccak256Hasher>(
+            &serialize_chain_transition(&transition),
+        )
+        .unwrap();
+
+        let (original_input, _) = transition.verify::<Keccak256Hasher>(false);
+        let (restored_input, _) = restored.verify::<Keccak256Hasher>(false);
+        assert_eq!(restored_input.newMmrRoot, original_input.newMmrRoot);
+        assert_eq!(
+            restored_input.previousMmrRoot,
+            original_input.previousMmrRoot
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_chain_transition_rejects_inconsistent_transition() {
+        let mut transition = genesis_extension_transition().await;
+        transition.current_mmr_root = [0xff; 32];
+
+        assert!(matches!(
+            deserialize_chain_transition::<Keccak256Hasher>(&serialize_chain_transition(
+                &transition
+            )),
+            Err(RiftSdkError::InvalidChainTransition(_))
+        ));
+        assert!(deserialize_chain_transition::<Keccak256Hasher>(b"not a transition").is_err());
+    }
+}
@@ -542,6 +542,26 @@ impl<H: LeafHasher> CheckpointedBlockTree<H> {
         self.indexed_mmr.get_leaf_by_leaf_hash(leaf_hash).await
     }
 
+    pub async fn contains_leaf_hash(&self, leaf_hash: &LeafDigest) -> Result<bool> {
+        self.indexed_mmr.contains_leaf_hash(leaf_hash).await
+    }
+
+    pub async fn get_leaf_by_block_hash(
+        &self,
+        block_hash: &[u8; 32],
+    ) -> Result<Option<(usize, BlockLeaf)>> {
+        self.indexed_mmr.get_leaf_by_block_hash(block_hash).await
+    }
+
+    pub async fn get_proven_leaf_by_leaf_hash(
+        &self,
+        leaf_hash: &LeafDigest,
+    ) -> Result<Option<(BlockLeaf, CircuitMMRProof)>> {
+        self.indexed_mmr
+            .get_proven_leaf_by_leaf_hash(leaf_hash)
+            .await
+    }
+
     pub async fn get_leaf_by_leaf_index(&self, leaf_index: usize) -> Result<Option<BlockLeaf>> {
         self.indexed_mmr.get_leaf_by_leaf_index(leaf_index).await
     }
@@ -550,6 +570,10 @@ impl<H: LeafHasher> CheckpointedBlockTree<H> {
         self.indexed_mmr.get_leaf_count().await
     }
 
+    pub async fn get_tip_leaf(&self) -> Result<Option<BlockLeaf>> {
+        self.indexed_mmr.get_tip_leaf().await
+    }
+
     pub async fn get_client_proof(
         &self,
         leaf_index: usize,
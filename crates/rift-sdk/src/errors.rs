@@ -26,8 +26,11 @@ pub enum RiftSdkError {
     #[error("Insufficient funds")]
     InsufficientFunds,
 
-    #[error("Header chain validation failed")]
-    HeaderChainValidationFailed,
+    #[error("Header chain validation failed: {0}")]
+    HeaderChainValidationFailed(String),
+
+    #[error("Invalid chain transition: {0}")]
+    InvalidChainTransition(String),
 
     #[error("Parent validation failed: {0}")]
     ParentValidationFailed(String),
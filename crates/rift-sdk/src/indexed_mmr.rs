@@ -2,11 +2,12 @@ use std::collections::HashMap;
 // TODO: low priority, make IndexedMMR clonable so that we can clone a built BchOverwriteMMRState instead of having to re-build it
 // for each swap (or maybe just rewind it???)
 use std::convert::TryInto;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use alloy::hex;
+use alloy::primitives::FixedBytes;
 use serde::{Deserialize, Serialize};
 
 use accumulators::mmr::{
@@ -28,6 +29,12 @@ use tracing::info;
 use crate::errors::{Result, RiftSdkError};
 use crate::DatabaseLocation;
 
+/// Set once every leaf is in the block hash index, so databases created before the index
+/// existed are backfilled on open
+const BLOCK_HASH_INDEX_BACKFILLED_KEY: &str = "blockHashIndexBackfilled";
+/// Leaves read per batch insert while backfilling the block hash index
+const BLOCK_HASH_INDEX_BACKFILL_CHUNK: usize = 10_000;
+
 // -----------------------------------------------------------------------------
 // Helpers
 // -----------------------------------------------------------------------------
@@ -37,6 +44,46 @@ pub fn digest_to_hex(digest: &LeafDigest) -> String {
     format!("0x{}", hex::encode(digest))
 }
 
+/// An MMR root, kept distinct from other 32-byte values like block hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MmrRoot(pub LeafDigest);
+
+impl fmt::Display for MmrRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", digest_to_hex(&self.0))
+    }
+}
+
+impl From<LeafDigest> for MmrRoot {
+    fn from(digest: LeafDigest) -> Self {
+        Self(digest)
+    }
+}
+
+impl From<MmrRoot> for LeafDigest {
+    fn from(root: MmrRoot) -> Self {
+        root.0
+    }
+}
+
+impl From<FixedBytes<32>> for MmrRoot {
+    fn from(bytes: FixedBytes<32>) -> Self {
+        Self(bytes.0)
+    }
+}
+
+impl From<MmrRoot> for FixedBytes<32> {
+    fn from(root: MmrRoot) -> Self {
+        FixedBytes(root.0)
+    }
+}
+
+/// Number of elements (leaves and internal nodes) in an MMR with `leaf_count` leaves, the
+/// `elements_count` to pass to `get_peaks` for the MMR as it was at that leaf count.
+pub fn leaf_count_to_elements_count(leaf_count: usize) -> usize {
+    2 * leaf_count - leaf_count.count_ones() as usize
+}
+
 /// Convert a `BlockLeaf` to a hex representation.
 pub fn leaf_to_hex(leaf: &BlockLeaf) -> String {
     let serialized = leaf.serialize();
@@ -239,6 +286,16 @@ impl ReverseIndex {
         }
     }
 
+    async fn contains_hash(&self, leaf_digest: &LeafDigest) -> Result<bool> {
+        let key = self.make_key(&digest_to_hex(leaf_digest));
+        let val_opt = self
+            .store
+            .get(&key)
+            .await
+            .map_err(|e| RiftSdkError::StoreError(format!("Store get error: {e}")))?;
+        Ok(val_opt.is_some())
+    }
+
     async fn delete_many(&self, leaf_hashes_hex: Vec<String>) -> Result<()> {
         if leaf_hashes_hex.is_empty() {
             return Ok(());
@@ -266,6 +323,7 @@ impl ReverseIndex {
 pub struct IndexedMMR<H: LeafHasher> {
     client_mmr: ClientMMR,                 // uses Keccak internally for MMR ops
     reverse_index: ReverseIndex,           // separate prefix
+    block_hash_index: ReverseIndex,        // same values as reverse_index, keyed by block hash
     _phantom: std::marker::PhantomData<H>, // we never store H itself, only use it generically
 }
 
@@ -320,11 +378,62 @@ impl<H: LeafHasher> IndexedMMR<H> {
         // 4) Build the reverse index
         let reverse_index = ReverseIndex::new(store.clone(), "revIndex:");
 
-        Ok(Self {
+        // 5) Build the block hash index
+        let block_hash_index = ReverseIndex::new(store.clone(), "blockHashIndex:");
+
+        let indexed_mmr = Self {
             client_mmr,
             reverse_index,
+            block_hash_index,
             _phantom: std::marker::PhantomData,
-        })
+        };
+        indexed_mmr
+            .backfill_block_hash_index(store.as_ref())
+            .await?;
+        Ok(indexed_mmr)
+    }
+
+    /// Adds the leaves appended before the block hash index existed to it, once per database.
+    async fn backfill_block_hash_index(&self, store: &(dyn Store + Send + Sync)) -> Result<()> {
+        let backfilled = store
+            .get(BLOCK_HASH_INDEX_BACKFILLED_KEY)
+            .await
+            .map_err(|e| RiftSdkError::StoreError(format!("Store get error: {e}")))?;
+        if backfilled.is_some() {
+            return Ok(());
+        }
+
+        let leaf_count = self.get_leaf_count().await?;
+        for chunk_start in (0..leaf_count).step_by(BLOCK_HASH_INDEX_BACKFILL_CHUNK) {
+            let chunk_end = (chunk_start + BLOCK_HASH_INDEX_BACKFILL_CHUNK).min(leaf_count);
+            let mut leaves = Vec::with_capacity(chunk_end - chunk_start);
+            for leaf_index in chunk_start..chunk_end {
+                let leaf = self
+                    .get_leaf_by_leaf_index(leaf_index)
+                    .await?
+                    .ok_or_else(|| {
+                        RiftSdkError::MMRError(format!("Leaf missing at index {leaf_index}"))
+                    })?;
+                leaves.push((map_leaf_index_to_element_index(leaf_index), leaf));
+            }
+            self.block_hash_index
+                .batch_insert(
+                    leaves
+                        .iter()
+                        .map(|(element_index, leaf)| (&leaf.block_hash, *element_index, leaf))
+                        .collect(),
+                )
+                .await?;
+        }
+        if leaf_count > 0 {
+            info!("Backfilled the block hash index with {} leaves", leaf_count);
+        }
+
+        store
+            .set(BLOCK_HASH_INDEX_BACKFILLED_KEY, "true")
+            .await
+            .map_err(|e| RiftSdkError::StoreError(format!("Store set error: {e}")))?;
+        Ok(())
     }
 
     /// Append or reorg based on a "parent" leaf; the first leaf in `leaves` is the parent.
@@ -354,6 +463,14 @@ impl<H: LeafHasher> IndexedMMR<H> {
 
     /// Rewind to a given leaf index, removing subsequent leaves.
     pub async fn rewind(&mut self, parent_leaf_index: usize) -> Result<()> {
+        let leaf_count = self.get_leaf_count().await?;
+        let mut pruned_block_hashes = Vec::new();
+        for leaf_index in parent_leaf_index + 1..leaf_count {
+            if let Some(leaf) = self.get_leaf_by_leaf_index(leaf_index).await? {
+                pruned_block_hashes.push(digest_to_hex(&leaf.block_hash));
+            }
+        }
+
         let pruned_leaf_hashes = self
             .client_mmr
             .rewind(parent_leaf_index)
@@ -361,6 +478,9 @@ impl<H: LeafHasher> IndexedMMR<H> {
             .map_err(|e| RiftSdkError::MMRError(format!("Failed to rewind: {e}")))?;
 
         self.reverse_index.delete_many(pruned_leaf_hashes).await?;
+        self.block_hash_index
+            .delete_many(pruned_block_hashes)
+            .await?;
         Ok(())
     }
 
@@ -401,6 +521,15 @@ impl<H: LeafHasher> IndexedMMR<H> {
 
         self.reverse_index.batch_insert(reverse_index_data).await?;
 
+        let block_hash_index_data = leaves
+            .iter()
+            .zip(&append_results)
+            .map(|(leaf, append_result)| (&leaf.block_hash, append_result.element_index, leaf))
+            .collect();
+        self.block_hash_index
+            .batch_insert(block_hash_index_data)
+            .await?;
+
         let total_duration = start_time.elapsed();
         info!(
             "Completed appending {} leaves in {} ({:.1} leaves/sec)",
@@ -481,6 +610,34 @@ impl<H: LeafHasher> IndexedMMR<H> {
         Ok(val_opt.map(|v| (v.element_index, v.leaf_data)))
     }
 
+    /// Find the leaf of the block with `block_hash` => returns (index, data).
+    pub async fn get_leaf_by_block_hash(
+        &self,
+        block_hash: &[u8; 32],
+    ) -> Result<Option<(usize, BlockLeaf)>> {
+        let val_opt = self.block_hash_index.get_by_hash(block_hash).await?;
+        Ok(val_opt.map(|v| (v.element_index, v.leaf_data)))
+    }
+
+    /// Whether a leaf with `leaf_hash` is in the MMR, without deserializing the leaf.
+    pub async fn contains_leaf_hash(&self, leaf_hash: &LeafDigest) -> Result<bool> {
+        self.reverse_index.contains_hash(leaf_hash).await
+    }
+
+    /// Find the leaf with `leaf_hash` and a proof of its inclusion in the current MMR.
+    pub async fn get_proven_leaf_by_leaf_hash(
+        &self,
+        leaf_hash: &LeafDigest,
+    ) -> Result<Option<(BlockLeaf, CircuitMMRProof)>> {
+        let Some((element_index, leaf)) = self.get_leaf_by_leaf_hash(leaf_hash).await? else {
+            return Ok(None);
+        };
+        let leaf_index = element_index_to_leaf_index(element_index)
+            .map_err(|e| RiftSdkError::MMRError(format!("Bad element_index: {e}")))?;
+        let proof = self.get_circuit_proof(leaf_index, None).await?;
+        Ok(Some((leaf, proof)))
+    }
+
     pub async fn get_leaf_by_leaf_index(&self, leaf_index: usize) -> Result<Option<BlockLeaf>> {
         // Get the hash at the leaf index
         let hash_opt = self
@@ -521,6 +678,23 @@ impl<H: LeafHasher> IndexedMMR<H> {
         Ok(Some(reverse_index.leaf_data))
     }
 
+    /// Get the leaf at the tip of the MMR, or `None` if the MMR is empty.
+    /// The leaf count and the tip lookup happen under the same borrow, so a caller holding a
+    /// single read guard never observes a tip from a different MMR state than the count.
+    pub async fn get_tip_leaf(&self) -> Result<Option<BlockLeaf>> {
+        let leaf_count = self.get_leaf_count().await?;
+        if leaf_count == 0 {
+            return Ok(None);
+        }
+        match self.get_leaf_by_leaf_index(leaf_count - 1).await? {
+            Some(leaf) => Ok(Some(leaf)),
+            None => Err(RiftSdkError::MMRError(format!(
+                "Tip leaf missing at index {}",
+                leaf_count - 1
+            ))),
+        }
+    }
+
     /// Return the internal accumulators MMR if needed.
     pub fn client_mmr(&self) -> &ClientMMR {
         &self.client_mmr
@@ -563,6 +737,23 @@ impl<H: LeafHasher> IndexedMMR<H> {
     pub async fn get_bagged_peak(&self) -> Result<LeafDigest> {
         bag_peaks::<H>(&self.client_mmr).await
     }
+
+    /// Whether the first `leaf_count` leaves of this MMR are exactly the leaves committed to by
+    /// `other_peaks`, the peaks of another MMR at the same leaf count. Peaks commit to every
+    /// leaf before them, so this checks the whole prefix rather than a single matching leaf.
+    pub async fn verify_shared_prefix(
+        &self,
+        leaf_count: usize,
+        other_peaks: &[LeafDigest],
+    ) -> Result<bool> {
+        if leaf_count == 0 || leaf_count > self.get_leaf_count().await? {
+            return Ok(false);
+        }
+        let peaks = self
+            .get_peaks(Some(leaf_count_to_elements_count(leaf_count)))
+            .await?;
+        Ok(peaks == other_peaks)
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -575,6 +766,154 @@ mod tests {
     use bitcoin_light_client_core::hasher::Keccak256Hasher;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_mmr_root_conversions_and_display() {
+        let mut digest = [0u8; 32];
+        digest[0] = 0xab;
+        digest[31] = 0x01;
+        let root = MmrRoot::from(digest);
+
+        assert_eq!(LeafDigest::from(root), digest);
+        let fixed_bytes: FixedBytes<32> = root.into();
+        assert_eq!(fixed_bytes, FixedBytes(digest));
+        assert_eq!(MmrRoot::from(fixed_bytes), root);
+        assert_eq!(
+            root.to_string(),
+            "0xab00000000000000000000000000000000000000000000000000000000000001"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_shared_prefix() -> Result<()> {
+        let leaves: Vec<BlockLeaf> = (0..12u32)
+            .map(|height| BlockLeaf::new([height as u8 + 1; 32], height, [height as u8; 32]))
+            .collect();
+        let mut forked_leaves = leaves.clone();
+        for leaf in &mut forked_leaves[7..] {
+            leaf.block_hash = [0xff - leaf.height as u8; 32];
+        }
+        let mut corrupt_leaves = leaves.clone();
+        corrupt_leaves[2].block_hash = [0xee; 32];
+
+        let mut mmr = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        mmr.batch_append(&leaves).await?;
+        let mut forked = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        forked.batch_append(&forked_leaves).await?;
+        let mut corrupt = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        corrupt.batch_append(&corrupt_leaves).await?;
+
+        for leaf_count in 1..=7 {
+            let forked_peaks = forked
+                .get_peaks(Some(leaf_count_to_elements_count(leaf_count)))
+                .await?;
+            assert!(mmr.verify_shared_prefix(leaf_count, &forked_peaks).await?);
+        }
+        for leaf_count in 8..=12 {
+            let forked_peaks = forked
+                .get_peaks(Some(leaf_count_to_elements_count(leaf_count)))
+                .await?;
+            assert!(!mmr.verify_shared_prefix(leaf_count, &forked_peaks).await?);
+        }
+
+        // same leaf at index 6, different history before it
+        assert_eq!(
+            mmr.get_leaf_by_leaf_index(6).await?,
+            corrupt.get_leaf_by_leaf_index(6).await?
+        );
+        let corrupt_peaks = corrupt
+            .get_peaks(Some(leaf_count_to_elements_count(7)))
+            .await?;
+        assert!(!mmr.verify_shared_prefix(7, &corrupt_peaks).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compute_root_after_append_matches_engine() -> Result<()> {
+        let leaves: Vec<BlockLeaf> = (0..17u32)
+            .map(|height| BlockLeaf::new([height as u8 + 1; 32], height, [height as u8; 32]))
+            .collect();
+        let mut mmr = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        mmr.batch_append(&leaves[..10]).await?;
+
+        let expected_root =
+            bitcoin_light_client_core::mmr::compute_root_after_append::<Keccak256Hasher>(
+                &mmr.get_peaks(None).await?,
+                mmr.get_leaf_count().await? as u32,
+                &leaves[10..],
+            );
+
+        mmr.batch_append(&leaves[10..]).await?;
+        assert_eq!(mmr.get_root().await?, expected_root);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_contains_leaf_hash() -> Result<()> {
+        let mut mmr = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        let leaves: Vec<BlockLeaf> = (0..4u8)
+            .map(|i| BlockLeaf::new([i + 1; 32], i as u32, [i; 32]))
+            .collect();
+        mmr.batch_append(&leaves).await?;
+
+        for leaf in &leaves {
+            let leaf_hash = leaf.hash::<Keccak256Hasher>();
+            assert!(mmr.contains_leaf_hash(&leaf_hash).await?);
+            assert!(mmr.get_leaf_by_leaf_hash(&leaf_hash).await?.is_some());
+        }
+        let unknown = BlockLeaf::new([9; 32], 9, [9; 32]).hash::<Keccak256Hasher>();
+        assert!(!mmr.contains_leaf_hash(&unknown).await?);
+
+        // rewound leaves are gone from both lookups
+        mmr.rewind(1).await?;
+        let rewound = leaves[3].hash::<Keccak256Hasher>();
+        assert!(!mmr.contains_leaf_hash(&rewound).await?);
+        assert!(mmr.get_leaf_by_leaf_hash(&rewound).await?.is_none());
+        assert!(
+            mmr.contains_leaf_hash(&leaves[1].hash::<Keccak256Hasher>())
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_leaf_by_block_hash() -> Result<()> {
+        let mut mmr = IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?;
+        let leaves: Vec<BlockLeaf> = (0..4u8)
+            .map(|i| BlockLeaf::new([i + 1; 32], i as u32, [i; 32]))
+            .collect();
+        mmr.batch_append(&leaves).await?;
+
+        for (leaf_index, leaf) in leaves.iter().enumerate() {
+            let (element_index, found) =
+                mmr.get_leaf_by_block_hash(&leaf.block_hash).await?.unwrap();
+            assert_eq!(element_index, map_leaf_index_to_element_index(leaf_index));
+            assert_eq!(found, *leaf);
+        }
+        assert!(mmr.get_leaf_by_block_hash(&[9; 32]).await?.is_none());
+
+        // rewound blocks are gone, a block appended in their place is found
+        mmr.rewind(1).await?;
+        assert!(mmr
+            .get_leaf_by_block_hash(&leaves[2].block_hash)
+            .await?
+            .is_none());
+        assert!(mmr
+            .get_leaf_by_block_hash(&leaves[3].block_hash)
+            .await?
+            .is_none());
+        let replacement = BlockLeaf::new([7; 32], 2, [7; 32]);
+        mmr.append(&replacement).await?;
+        assert_eq!(
+            mmr.get_leaf_by_block_hash(&replacement.block_hash).await?,
+            Some((map_leaf_index_to_element_index(2), replacement))
+        );
+        assert!(mmr
+            .get_leaf_by_block_hash(&leaves[1].block_hash)
+            .await?
+            .is_some());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_in_memory_open() -> Result<()> {
         // 1) Create or open the MMR
@@ -602,6 +941,62 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_tip_leaf_consistent_during_concurrent_shrink() -> Result<()> {
+        let leaves: Vec<BlockLeaf> = (0..20u32)
+            .map(|height| BlockLeaf {
+                block_hash: [height as u8; 32],
+                cumulative_chainwork: [height as u8; 32],
+                height,
+            })
+            .collect();
+
+        let mmr = Arc::new(tokio::sync::RwLock::new(
+            IndexedMMR::<Keccak256Hasher>::open(&DatabaseLocation::InMemory).await?,
+        ));
+        assert!(mmr.read().await.get_tip_leaf().await?.is_none());
+        mmr.write().await.batch_append(&leaves).await?;
+
+        // repeatedly shrink the MMR to half its size and grow it back
+        let writer_mmr = mmr.clone();
+        let writer_leaves = leaves.clone();
+        let writer = tokio::spawn(async move {
+            for _ in 0..10 {
+                writer_mmr.write().await.rewind(9).await?;
+                tokio::task::yield_now().await;
+                writer_mmr
+                    .write()
+                    .await
+                    .batch_append(&writer_leaves[10..])
+                    .await?;
+                tokio::task::yield_now().await;
+            }
+            Ok::<(), RiftSdkError>(())
+        });
+
+        let mut observed_tips = std::collections::HashSet::new();
+        while !writer.is_finished() {
+            let guard = mmr.read().await;
+            let tip = guard
+                .get_tip_leaf()
+                .await?
+                .expect("MMR should not be empty");
+            let leaf_count = guard.get_leaf_count().await?;
+            assert_eq!(tip.height as usize, leaf_count - 1);
+            assert_eq!(tip, leaves[tip.height as usize]);
+            observed_tips.insert(tip.height);
+            drop(guard);
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap()?;
+
+        assert!(observed_tips
+            .iter()
+            .all(|height| *height == 9 || *height == 19));
+        assert_eq!(mmr.read().await.get_tip_leaf().await?, Some(leaves[19]));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sqlite_open_and_hasher_check() -> Result<()> {
         let tmp = tempdir().unwrap();
@@ -1,4 +1,5 @@
 pub mod bitcoin_utils;
+pub mod chain_transition;
 pub mod checkpoint_mmr;
 mod errors;
 pub mod indexed_mmr;
@@ -16,7 +17,7 @@ use alloy::signers::local::LocalSigner;
 use alloy::signers::Signer;
 use alloy::transports::{impl_future, TransportResult};
 use alloy::{providers::Provider, pubsub::PubSubFrontend};
-use backoff::exponential::ExponentialBackoff;
+use backoff::exponential::{ExponentialBackoff, ExponentialBackoffBuilder};
 use bitcoin::hashes::hex::FromHex;
 use rift_core::giga::RiftProgramInput;
 use sol_bindings::RiftExchange::RiftExchangeInstance;
@@ -24,9 +25,10 @@ use sp1_sdk::{include_elf, ProverClient, SP1ProofWithPublicValues, SP1Stdin, SP1
 use sp1_sdk::{EnvProver, HashableKey};
 use sp1_sdk::{Prover, SP1ProvingKey};
 use std::fmt::Write;
+use std::future::Future;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub type WebsocketWalletProvider = FillProvider<
     JoinFill<
@@ -107,31 +109,96 @@ impl FromStr for DatabaseLocation {
     }
 }
 
+/// Controls how a dropped websocket connection is re-established.
+/// `max_attempts` of `None` retries until `max_elapsed_time` runs out.
 #[derive(Clone, Debug)]
-pub struct RetryWsConnect(WsConnect);
+pub struct WsReconnectPolicy {
+    pub max_attempts: Option<u32>,
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub max_elapsed_time: Option<Duration>,
+}
+
+impl Default for WsReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(60),
+            max_elapsed_time: Some(Duration::from_secs(15 * 60)),
+        }
+    }
+}
+
+impl WsReconnectPolicy {
+    fn backoff(&self) -> ExponentialBackoff<backoff::SystemClock> {
+        ExponentialBackoffBuilder::new()
+            .with_initial_interval(self.initial_interval)
+            .with_max_interval(self.max_interval)
+            .with_max_elapsed_time(self.max_elapsed_time)
+            .build()
+    }
+}
+
+async fn retry_with_policy<T, E, F, Fut>(
+    policy: &WsReconnectPolicy,
+    mut operation: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempts = 0;
+    backoff::future::retry(policy.backoff(), || {
+        attempts += 1;
+        let exhausted = policy.max_attempts.is_some_and(|max| attempts >= max);
+        let attempt = operation();
+        async move {
+            attempt.await.map_err(|e| {
+                if exhausted {
+                    backoff::Error::permanent(e)
+                } else {
+                    backoff::Error::transient(e)
+                }
+            })
+        }
+    })
+    .await
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryWsConnect {
+    ws: WsConnect,
+    policy: WsReconnectPolicy,
+}
+
+impl RetryWsConnect {
+    pub fn new(ws: WsConnect, policy: WsReconnectPolicy) -> Self {
+        Self { ws, policy }
+    }
+}
 
 impl PubSubConnect for RetryWsConnect {
     fn is_local(&self) -> bool {
-        self.0.is_local()
+        self.ws.is_local()
     }
 
     fn connect(&self) -> impl_future!(<Output = TransportResult<ConnectionHandle>>) {
-        self.0.connect()
+        self.ws.connect()
     }
 
     async fn try_reconnect(&self) -> TransportResult<ConnectionHandle> {
-        backoff::future::retry(
-            ExponentialBackoff::<backoff::SystemClock>::default(),
-            || async { Ok(self.0.try_reconnect().await?) },
-        )
-        .await
+        retry_with_policy(&self.policy, || self.ws.try_reconnect()).await
     }
 }
 
 pub async fn create_websocket_provider(
     evm_rpc_websocket_url: &str,
 ) -> errors::Result<impl Provider<PubSubFrontend>> {
-    let ws = RetryWsConnect(WsConnect::new(evm_rpc_websocket_url));
+    let ws = RetryWsConnect::new(
+        WsConnect::new(evm_rpc_websocket_url),
+        WsReconnectPolicy::default(),
+    );
     let client = ClientBuilder::default()
         .pubsub(ws)
         .await
@@ -155,7 +222,20 @@ pub async fn create_websocket_wallet_provider(
     evm_rpc_websocket_url: &str,
     private_key: [u8; 32],
 ) -> errors::Result<WebsocketWalletProvider> {
-    let ws = RetryWsConnect(WsConnect::new(evm_rpc_websocket_url));
+    create_websocket_wallet_provider_with_reconnect_policy(
+        evm_rpc_websocket_url,
+        private_key,
+        WsReconnectPolicy::default(),
+    )
+    .await
+}
+
+pub async fn create_websocket_wallet_provider_with_reconnect_policy(
+    evm_rpc_websocket_url: &str,
+    private_key: [u8; 32],
+    reconnect_policy: WsReconnectPolicy,
+) -> errors::Result<WebsocketWalletProvider> {
+    let ws = RetryWsConnect::new(WsConnect::new(evm_rpc_websocket_url), reconnect_policy);
     let client = ClientBuilder::default()
         .pubsub(ws)
         .await
@@ -171,3 +251,47 @@ pub async fn create_websocket_wallet_provider(
 
     Ok(provider)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_attempts: Option<u32>) -> WsReconnectPolicy {
+        WsReconnectPolicy {
+            max_attempts,
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(1),
+            max_elapsed_time: Some(Duration::from_secs(5)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_recovers_after_single_disconnect() {
+        let attempts = AtomicU32::new(0);
+        let result = retry_with_policy(&fast_policy(Some(3)), || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err("connection dropped")
+            } else {
+                Ok("reconnected")
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("reconnected"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = retry_with_policy(&fast_policy(Some(3)), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err("connection dropped")
+        })
+        .await;
+
+        assert_eq!(result, Err("connection dropped"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,12 +1,19 @@
 use crate::RIFT_PROGRAM_ELF;
+use bitcoin_light_client_core::ChainTransition;
 use rift_core::giga::RiftProgramInput;
 use sp1_sdk::{
     include_elf, EnvProver, HashableKey, Prover, ProverClient, SP1ProofWithPublicValues,
     SP1ProvingKey, SP1Stdin, SP1VerifyingKey,
 };
+use std::collections::VecDeque;
 use std::str::FromStr;
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Prover network rpc sp1-sdk connects to when `NETWORK_RPC_URL` isn't set
+const DEFAULT_NETWORK_RPC_URL: &str = "https://rpc.production.succinct.xyz/";
+/// Bounds `check_reachable`, which is polled while the prover is down
+const REACHABILITY_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct RiftProofGenerator {
     pub pk: Arc<SP1ProvingKey>,
@@ -14,6 +21,7 @@ pub struct RiftProofGenerator {
     pub prover_client: Arc<EnvProver>,
     pub circuit_verification_key_hash: [u8; 32],
     pub prover_type: ProofGeneratorType,
+    proving_time_estimator: Arc<Mutex<ProvingTimeEstimator>>,
 }
 
 impl RiftProofGenerator {
@@ -46,9 +54,19 @@ impl RiftProofGenerator {
             circuit_verification_key_hash,
             prover_type,
             prover_client: Arc::new(prover_client),
+            proving_time_estimator: Arc::new(Mutex::new(ProvingTimeEstimator::default())),
         }
     }
 
+    /// Estimates how long proving `transition` will take, calibrated from the durations
+    /// of proofs previously generated by this instance.
+    pub fn estimate_proving_time(&self, transition: &ChainTransition) -> Duration {
+        self.proving_time_estimator
+            .lock()
+            .unwrap()
+            .estimate_proving_time(transition)
+    }
+
     /// Executes or proves the program with the given configuration for the provided `RiftProgramInput`.
     /// This method now runs on a dedicated thread (using `spawn_blocking`) so it does not
     /// block the async executor's main threads.
@@ -60,6 +78,10 @@ impl RiftProofGenerator {
         let pk = self.pk.clone();
         let prover_type = self.prover_type;
         let prover_client = self.prover_client.clone();
+        let header_count = input
+            .light_client_input
+            .as_ref()
+            .map(|transition| transition.new_headers.len());
 
         let input = input.clone();
 
@@ -74,10 +96,10 @@ impl RiftProofGenerator {
             // Perform the CPU-intensive operation synchronously
             let proof_outcome = match prover_type {
                 ProofGeneratorType::Execute => {
-                    let (_output, report) =
-                        prover_client.execute(RIFT_PROGRAM_ELF, &stdin).run()?;
+                    let (output, report) = prover_client.execute(RIFT_PROGRAM_ELF, &stdin).run()?;
                     Proof {
                         proof_type: ProofGeneratorType::Execute,
+                        public_values: output.to_vec(),
                         proof: None,
                         cycles: Some(report.total_instruction_count()),
                         duration: start.elapsed(),
@@ -89,6 +111,7 @@ impl RiftProofGenerator {
                     let sp1_proof = prover_client.prove(&pk, &stdin).groth16().run()?;
                     Proof {
                         proof_type: prover_type,
+                        public_values: sp1_proof.public_values.to_vec(),
                         proof: Some(sp1_proof),
                         cycles: None,
                         duration: start.elapsed(),
@@ -106,9 +129,43 @@ impl RiftProofGenerator {
         })
         .await?; // first `?` handles JoinError from the spawned task
 
+        if let (Ok(proof), Some(header_count)) = (&proof_result, header_count) {
+            self.proving_time_estimator
+                .lock()
+                .unwrap()
+                .record(header_count, proof.duration);
+        }
+
         // The returned value is `Result<Proof, Box<dyn std::error::Error + Send + Sync>>`
         proof_result
     }
+
+    /// Verifies a generated proof against this generator's verifying key.
+    /// Executed (mock) proofs carry no proof and are accepted as is.
+    pub fn verify(&self, proof: &Proof) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match &proof.proof {
+            Some(sp1_proof) => Ok(self.prover_client.verify(sp1_proof, &self.vk)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the prover can be reached without proving anything, so it's cheap to poll.
+    /// Only the network prover is remote, any response from the prover network counts as
+    /// reachable. The local provers run in process and are always reachable.
+    pub async fn check_reachable(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.prover_type != ProofGeneratorType::ProveNetwork {
+            return Ok(());
+        }
+        let rpc_url = std::env::var("NETWORK_RPC_URL")
+            .unwrap_or_else(|_| DEFAULT_NETWORK_RPC_URL.to_string());
+        reqwest::Client::builder()
+            .timeout(REACHABILITY_CHECK_TIMEOUT)
+            .build()?
+            .get(rpc_url)
+            .send()
+            .await?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -136,11 +193,101 @@ impl FromStr for ProofGeneratorType {
 #[derive(Debug)]
 pub struct Proof {
     pub proof_type: ProofGeneratorType,
+    /// Public values committed by the program, present for executed (mock) proofs too
+    pub public_values: Vec<u8>,
     pub proof: Option<SP1ProofWithPublicValues>,
     pub cycles: Option<u64>,
     pub duration: std::time::Duration,
 }
 
+/// Number of (header count, duration) samples kept for the rolling regression.
+const PROVING_TIME_SAMPLE_WINDOW: usize = 32;
+/// Fixed proving overhead assumed before any proofs have been observed.
+const DEFAULT_BASE_PROVING_TIME: Duration = Duration::from_secs(60);
+/// Per-header proving cost assumed before any proofs have been observed.
+const DEFAULT_PER_HEADER_PROVING_TIME: Duration = Duration::from_millis(500);
+
+/// Estimates proving time from the number of headers in a `ChainTransition` using a
+/// least squares fit over the most recently observed proof durations.
+#[derive(Debug, Clone)]
+pub struct ProvingTimeEstimator {
+    samples: VecDeque<(usize, Duration)>,
+    window: usize,
+}
+
+impl Default for ProvingTimeEstimator {
+    fn default() -> Self {
+        Self::new(PROVING_TIME_SAMPLE_WINDOW)
+    }
+}
+
+impl ProvingTimeEstimator {
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(1),
+        }
+    }
+
+    /// Records an observed proof duration for a transition with `header_count` new headers,
+    /// evicting the oldest sample once the window is full.
+    pub fn record(&mut self, header_count: usize, duration: Duration) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((header_count, duration));
+    }
+
+    pub fn estimate_proving_time(&self, transition: &ChainTransition) -> Duration {
+        self.estimate_for_header_count(transition.new_headers.len())
+    }
+
+    pub fn estimate_for_header_count(&self, header_count: usize) -> Duration {
+        let (base, per_header) = self.fit();
+        Duration::from_secs_f64((base + per_header * header_count as f64).max(0.0))
+    }
+
+    // Returns (intercept, slope) in seconds, falling back to the defaults for whichever
+    // term the samples can't determine.
+    fn fit(&self) -> (f64, f64) {
+        let default_base = DEFAULT_BASE_PROVING_TIME.as_secs_f64();
+        let default_slope = DEFAULT_PER_HEADER_PROVING_TIME.as_secs_f64();
+        if self.samples.is_empty() {
+            return (default_base, default_slope);
+        }
+
+        let n = self.samples.len() as f64;
+        let mean_x = self.samples.iter().map(|(x, _)| *x as f64).sum::<f64>() / n;
+        let mean_y = self
+            .samples
+            .iter()
+            .map(|(_, y)| y.as_secs_f64())
+            .sum::<f64>()
+            / n;
+        let (covariance, variance) =
+            self.samples
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                    let dx = *x as f64 - mean_x;
+                    (
+                        covariance + dx * (y.as_secs_f64() - mean_y),
+                        variance + dx * dx,
+                    )
+                });
+
+        // All samples share a header count, so only the scale of the slope is unknown:
+        // attribute the observed time proportionally to the default cost model.
+        if variance == 0.0 {
+            let default_at_mean = default_base + default_slope * mean_x;
+            let scale = mean_y / default_at_mean;
+            return (default_base * scale, default_slope * scale);
+        }
+
+        let slope = (covariance / variance).max(0.0);
+        ((mean_y - slope * mean_x).max(0.0), slope)
+    }
+}
+
 /// Format a `Duration` for pretty printing in results.
 pub fn format_duration(duration: std::time::Duration) -> String {
     if duration.as_secs() == 0 {
@@ -154,3 +301,51 @@ pub fn format_duration(duration: std::time::Duration) -> String {
     }
     format!("{:.2} h", duration.as_secs_f64() / 3600.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_light_client_core::light_client::Header;
+
+    fn transition_with_headers(header_count: usize) -> ChainTransition {
+        ChainTransition {
+            new_headers: vec![Header::default(); header_count],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_scales_with_header_count() {
+        let mut estimator = ProvingTimeEstimator::default();
+        assert!(
+            estimator.estimate_proving_time(&transition_with_headers(100))
+                > estimator.estimate_proving_time(&transition_with_headers(10))
+        );
+
+        // 10s fixed + 100ms per header
+        for header_count in [1, 10, 50, 100, 500] {
+            estimator.record(
+                header_count,
+                Duration::from_secs(10) + Duration::from_millis(100) * header_count as u32,
+            );
+        }
+
+        let small = estimator.estimate_proving_time(&transition_with_headers(10));
+        let large = estimator.estimate_proving_time(&transition_with_headers(1000));
+        assert!(large > small);
+        assert!((small.as_secs_f64() - 11.0).abs() < 0.01);
+        assert!((large.as_secs_f64() - 110.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_estimate_uses_rolling_window() {
+        let mut estimator = ProvingTimeEstimator::new(2);
+        estimator.record(10, Duration::from_secs(1000));
+        estimator.record(10, Duration::from_secs(10));
+        estimator.record(20, Duration::from_secs(20));
+
+        // the 1000s outlier has been evicted, leaving a 1s per header fit
+        let estimate = estimator.estimate_for_header_count(30);
+        assert!((estimate.as_secs_f64() - 30.0).abs() < 0.01);
+    }
+}
@@ -159,7 +159,7 @@ async fn test_simulated_swap_end_to_end() {
     let safe_leaf: sol_bindings::Types::BlockLeaf = safe_leaf.into();
 
     println!("Safe leaf tip (data engine): {:?}", safe_leaf);
-    println!("Mmr root (data engine): {:?}", hex::encode(mmr_root));
+    println!("Mmr root (data engine): {}", mmr_root);
 
     let light_client_height = devnet
         .ethereum
@@ -551,7 +551,12 @@ async fn test_simulated_swap_end_to_end() {
         .collect::<Vec<Header>>();
 
     let chain_transition = ChainTransition {
-        current_mmr_root: devnet.contract_data_engine.get_mmr_root().await.unwrap(),
+        current_mmr_root: devnet
+            .contract_data_engine
+            .get_mmr_root()
+            .await
+            .unwrap()
+            .into(),
         current_mmr_bagged_peak: devnet
             .contract_data_engine
             .get_mmr_bagged_peak()
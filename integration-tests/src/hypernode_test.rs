@@ -116,7 +116,7 @@ async fn test_hypernode_simple_swap() {
     let safe_leaf: sol_bindings::Types::BlockLeaf = safe_leaf.into();
 
     println!("Safe leaf tip (data engine): {:?}", safe_leaf);
-    println!("Mmr root (data engine): {:?}", hex::encode(mmr_root));
+    println!("Mmr root (data engine): {}", mmr_root);
 
     let light_client_height = devnet
         .ethereum
@@ -308,6 +308,7 @@ async fn test_hypernode_simple_swap() {
     let hypernode_handle = tokio::spawn(async move {
         let hypernode_args = HypernodeArgs {
             evm_ws_rpc: devnet.ethereum.anvil.ws_endpoint_url().to_string(),
+            evm_fallback_ws_rpcs: vec![],
             btc_rpc: rpc_url_with_cookie.clone(),
             private_key: hex::encode(hypernode_account.secret_bytes),
             checkpoint_file: devnet.checkpoint_file_path.clone(),
@@ -316,6 +317,22 @@ async fn test_hypernode_simple_swap() {
             deploy_block_number: 0,
             btc_batch_rpc_size: 100,
             proof_generator: ProofGeneratorType::Execute,
+            shadow_proof_generator: None,
+            evm_ws_reconnect_max_attempts: None,
+            evm_ws_reconnect_max_backoff_secs: 60,
+            evm_chain_id: None,
+            skip_simulation_after_successes: None,
+            verify_proofs_locally: false,
+            prover_call_retries: 2,
+            max_calldata_bytes: hypernode::txn_broadcast::DEFAULT_MAX_CALLDATA_BYTES,
+            banned_block_hashes: vec![],
+            maintenance_windows: vec![],
+            light_client_settle_delay_secs: 0,
+            max_proofs_per_hour: None,
+            trusted_esplora_url: None,
+            trusted_tip_check_interval_secs: 60,
+            trusted_tip_alert_interval_secs: 600,
+            trusted_tip_config_file: None,
         };
         hypernode::run(hypernode_args)
             .await
@@ -341,6 +358,12 @@ async fn test_hypernode_simple_swap() {
         }
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     };
+    // the swap's payment block is newer than the light client, so the proof had to advance it
+    let proven_mmr_root = rift_exchange.mmrRoot().call().await.unwrap()._0;
+    assert_ne!(
+        proven_mmr_root, mmr_root,
+        "Light client root did not advance with the swap proof"
+    );
     // Now warp ahead on the eth chain to the timestamp that unlocks the swap
     let swap_unlock_timestamp = otc_swap.swap_proofs[0].swap.liquidityUnlockTimestamp;
     devnet
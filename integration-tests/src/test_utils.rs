@@ -166,7 +166,7 @@ pub async fn create_deposit(
     let safe_leaf: sol_bindings::Types::BlockLeaf = safe_leaf.into();
 
     println!("Safe leaf tip (data engine): {:?}", safe_leaf);
-    println!("Mmr root (data engine): {:?}", hex::encode(mmr_root));
+    println!("Mmr root (data engine): {}", mmr_root);
 
     let light_client_height = devnet
         .ethereum
@@ -0,0 +1,29 @@
+//! Converts a JSON header fixture (height -> header hex) into the flat binary format read by
+//! `load_test_headers_binary`.
+//!
+//! Usage: headers_json_to_binary <input.json> <output.bin>
+
+use test_data_utils::{encode_headers_binary, parse_headers_json};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <input.json> <output.bin>", args[0]);
+        std::process::exit(1);
+    }
+
+    let json_str = std::fs::read_to_string(&args[1]).expect("Failed to read JSON fixture");
+    let headers = parse_headers_json(&json_str);
+    let Some((start_height, _)) = headers.first() else {
+        eprintln!("No headers in {}", args[1]);
+        std::process::exit(1);
+    };
+
+    std::fs::write(&args[2], encode_headers_binary(&headers)).expect("Failed to write binary");
+    println!(
+        "Wrote {} headers to {}, start height {}",
+        headers.len(),
+        args[2],
+        start_height
+    );
+}
@@ -1,6 +1,7 @@
 use bitcoin::consensus::encode::deserialize;
 use bitcoin::Block;
 use hex::FromHex;
+use std::path::Path;
 
 use once_cell::sync::Lazy;
 use serde_json::Value;
@@ -41,21 +42,19 @@ fn load_test_headers(exhaustive: bool) -> Vec<(u32, [u8; 80])> {
     const INITIAL_BLOCK_HEADERS_STR: &str = include_str!("../data/headers_0_9999.json");
     const EXHAUSTIVE_BLOCK_HEADERS_STR: &str = include_str!("../data/headers_10000_800000.json");
     let start = std::time::Instant::now();
-    let mut json: Value =
-        serde_json::from_str(INITIAL_BLOCK_HEADERS_STR).expect("Failed to parse JSON");
-    let obj: &mut serde_json::Map<String, Value> =
-        json.as_object_mut().expect("JSON must be an object");
+    let mut headers = parse_headers_json(INITIAL_BLOCK_HEADERS_STR);
     if exhaustive {
-        obj.extend(
-            serde_json::from_str::<Value>(EXHAUSTIVE_BLOCK_HEADERS_STR)
-                .expect("Failed to parse JSON")
-                .as_object()
-                .expect("JSON must be an object")
-                .iter()
-                .map(|(k, v)| (k.clone(), v.clone())),
-        );
+        headers.extend(parse_headers_json(EXHAUSTIVE_BLOCK_HEADERS_STR));
     }
+    headers.sort_by_key(|(height, _)| *height);
+    println!("Time to load headers: {:?}", start.elapsed());
+    headers
+}
 
+/// Parses a JSON object of height -> header hex, sorted by height
+pub fn parse_headers_json(json_str: &str) -> Vec<(u32, [u8; 80])> {
+    let json: Value = serde_json::from_str(json_str).expect("Failed to parse JSON");
+    let obj = json.as_object().expect("JSON must be an object");
     let mut headers = Vec::with_capacity(obj.len());
     for (height_str, header_hex) in obj {
         let height = height_str.parse::<u32>().expect("Invalid height value");
@@ -65,10 +64,48 @@ fn load_test_headers(exhaustive: bool) -> Vec<(u32, [u8; 80])> {
         }
     }
     headers.sort_by_key(|(height, _)| *height);
-    println!("Time to load headers: {:?}", start.elapsed());
     headers
 }
 
+/// Concatenates consecutive headers into the flat binary fixture format read by
+/// `load_test_headers_binary`, the height of the first header is stored separately
+pub fn encode_headers_binary(headers: &[(u32, [u8; 80])]) -> Vec<u8> {
+    for window in headers.windows(2) {
+        assert_eq!(
+            window[1].0,
+            window[0].0 + 1,
+            "Binary fixtures require consecutive heights"
+        );
+    }
+    headers.iter().flat_map(|(_, header)| *header).collect()
+}
+
+/// Loads a flat file of concatenated 80 byte headers, the first being at `start_height`.
+/// Much faster than the JSON fixtures for the exhaustive header set.
+pub fn load_test_headers_binary(
+    path: impl AsRef<Path>,
+    start_height: u32,
+) -> std::io::Result<Vec<(u32, [u8; 80])>> {
+    let start = std::time::Instant::now();
+    let bytes = std::fs::read(path)?;
+    if bytes.len() % 80 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Header fixture is {} bytes, not a multiple of 80",
+                bytes.len()
+            ),
+        ));
+    }
+    let headers = bytes
+        .chunks_exact(80)
+        .zip(start_height..)
+        .map(|(header, height)| (height, header.try_into().unwrap()))
+        .collect();
+    println!("Time to load headers: {:?}", start.elapsed());
+    Ok(headers)
+}
+
 fn load_test_bch_headers() -> Vec<(u32, [u8; 80])> {
     const INITIAL_BLOCK_HEADERS_STR: &str = include_str!("../data/bch_headers_478559_578559.json");
     let mut json: Value =
@@ -85,3 +122,19 @@ fn load_test_bch_headers() -> Vec<(u32, [u8; 80])> {
     }
     headers
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_and_json_loaders_match() {
+        let path = std::env::temp_dir().join(format!("test_headers_{}.bin", std::process::id()));
+        std::fs::write(&path, encode_headers_binary(&TEST_HEADERS)).unwrap();
+
+        let binary_headers = load_test_headers_binary(&path, TEST_HEADERS[0].0).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(binary_headers, *TEST_HEADERS);
+    }
+}